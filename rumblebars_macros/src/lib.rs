@@ -0,0 +1,698 @@
+//! Derive support for rumblebars' `HBData` trait.
+//!
+//! ```ignore
+//! #[macro_use] extern crate rumblebars_macros;
+//! extern crate rumblebars;
+//!
+//! #[derive(HBData)]
+//! struct User {
+//!   name: String,
+//!   #[hbdata(rename = "e-mail")]
+//!   email: String,
+//!   #[hbdata(skip)]
+//!   password_hash: String,
+//! }
+//! ```
+//!
+//! Structs with named fields become branches (fields are keys), newtypes delegate
+//! to their inner value, tuple structs become arrays. For enums, unit variants render
+//! their name, newtype variants delegate to their value, struct variants are branches
+//! and tuple variants are arrays.
+//!
+//! Key lookups are plain `match` on the key string, nothing is allocated or copied.
+//!
+//! `template!` parses a template at compile time and expands to the `Template`,
+//! built without parsing at runtime. The template is a string literal or a file,
+//! relative to the crate root. Parse errors are compiler errors.
+//!
+//! ```ignore
+//! let page = template!("<h1>{{title}}</h1>{{#items}}<p>{{.}}</p>{{/items}}");
+//! let layout = template!(file = "templates/layout.hbs");
+//! ```
+//!
+//! `typed_template!` also binds the template to the type of its data, every path
+//! of the template is checked against the keys of derived structs, following the
+//! data of `{{#each}}` and `{{#with}}` blocks, `../` and `@root`. Missing keys are
+//! compiler errors on the template. Paths into json values, maps and enums are
+//! checked at runtime only, helpers used without parameters are declared first.
+//!
+//! ```ignore
+//! let post = typed_template!(Post, "<h1>{{title}}</h1>{{#each tags}}<a href=\"/{{@root.slug}}/{{.}}\">{{.}}</a>{{/each}}");
+//! let form = typed_template!(Post, helpers = [csrf_token], "<input value=\"{{csrf_token}}\">");
+//! ```
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate syn;
+#[macro_use] extern crate quote;
+extern crate rumblebars;
+
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as Tokens, Span};
+use syn::{DeriveInput, Data, DataStruct, Fields, Field, Attribute, Meta, NestedMeta, Lit, LitStr, Ident, Index};
+use syn::parse::{Parse, ParseStream};
+
+#[proc_macro_derive(HBData, attributes(hbdata))]
+pub fn derive_hbdata(input: TokenStream) -> TokenStream {
+  let ast: DeriveInput = match syn::parse(input) {
+    Ok(ast) => ast,
+    Err(e) => return e.to_compile_error().into(),
+  };
+
+  match expand_hbdata(&ast) {
+    Ok(tokens) => tokens.into(),
+    Err(e) => e.to_compile_error().into(),
+  }
+}
+
+// `#[hbdata(...)]` options of a field or variant
+struct FieldOptions {
+  rename: Option<String>,
+  skip: bool,
+}
+
+fn field_options(attrs: &[Attribute]) -> syn::Result<FieldOptions> {
+  let mut options = FieldOptions { rename: None, skip: false };
+
+  for attr in attrs.iter().filter(|a| a.path.is_ident("hbdata")) {
+    match try!(attr.parse_meta()) {
+      Meta::List(list) => {
+        for nested in list.nested.iter() {
+          match nested {
+            &NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("rename") => {
+              match nv.lit {
+                Lit::Str(ref s) => options.rename = Some(s.value()),
+                ref other => return Err(syn::Error::new_spanned(other, "hbdata rename expects a string literal")),
+              }
+            },
+            &NestedMeta::Meta(Meta::Path(ref p)) if p.is_ident("skip") => {
+              options.skip = true;
+            },
+            other => return Err(syn::Error::new_spanned(other, "unknown hbdata option, expected `rename = \"...\"` or `skip`")),
+          }
+        }
+      },
+      other => return Err(syn::Error::new_spanned(other, "expected #[hbdata(...)]")),
+    }
+  }
+
+  Ok(options)
+}
+
+// (key, accessor) pairs for named fields, skipped fields are left out
+fn named_keys(fields: &[&Field]) -> syn::Result<Vec<(String, Ident)>> {
+  let mut keys = Vec::new();
+  for field in fields.iter() {
+    let options = try!(field_options(&field.attrs));
+    if options.skip { continue; }
+    let ident = field.ident.clone().unwrap();
+    let key = options.rename.unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+    keys.push((key, ident));
+  }
+  Ok(keys)
+}
+
+fn expand_hbdata(ast: &DeriveInput) -> syn::Result<Tokens> {
+  let name = &ast.ident;
+  let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+  let body = match ast.data {
+    Data::Struct(ref data) => try!(expand_struct(&data.fields)),
+    Data::Enum(ref data) => {
+      let mut arms = Vec::new();
+      for variant in data.variants.iter() {
+        let options = try!(field_options(&variant.attrs));
+        let variant_name = options.rename.unwrap_or_else(|| variant.ident.to_string());
+        arms.push((&variant.ident, variant_name, &variant.fields));
+      }
+      try!(expand_enum(name, &arms))
+    },
+    Data::Union(_) => return Err(syn::Error::new_spanned(ast, "HBData cannot be derived for unions")),
+  };
+
+  // key types, for paths checked by `typed_template!`
+  let fields = match ast.data {
+    Data::Struct(DataStruct { fields: Fields::Named(ref named), .. }) => {
+      let mut impls = Vec::new();
+      for field in named.named.iter() {
+        let options = try!(field_options(&field.attrs));
+        if options.skip { continue; }
+        let key = key_type(&options.rename.unwrap_or_else(|| field.ident.as_ref().unwrap().to_string().trim_start_matches("r#").to_string()));
+        let ty = &field.ty;
+        impls.push(quote! {
+          impl #impl_generics ::rumblebars::HBField<#key> for #name #ty_generics #where_clause {
+            type Value = #ty;
+          }
+        });
+      }
+      quote! { #( #impls )* }
+    },
+    Data::Enum(_) => {
+      let mut generics = ast.generics.clone();
+      generics.params.push(syn::parse_quote!(__HBKey));
+      let (field_generics, _, _) = generics.split_for_impl();
+      quote! {
+        impl #field_generics ::rumblebars::HBField<__HBKey> for #name #ty_generics #where_clause {
+          type Value = ::rumblebars::UncheckedData;
+        }
+      }
+    },
+    _ => quote! {},
+  };
+
+  Ok(quote! {
+    impl #impl_generics ::rumblebars::HBData for #name #ty_generics #where_clause {
+      #body
+    }
+
+    #fields
+  })
+}
+
+// `key` as a `HBField` key, a tuple of its characters
+fn key_type(key: &str) -> Tokens {
+  let chars = key.chars().map(proc_macro2::Literal::character);
+  quote! { ( #( ::rumblebars::KeyChar<#chars>, )* ) }
+}
+
+fn expand_struct(fields: &Fields) -> syn::Result<Tokens> {
+  Ok(match *fields {
+    Fields::Named(ref named) => {
+      let fields: Vec<&Field> = named.named.iter().collect();
+      let keys = try!(named_keys(&fields));
+      let key_strs: Vec<&String> = keys.iter().map(|&(ref k, _)| k).collect();
+      let key_strs2 = key_strs.clone();
+      let idents: Vec<&Ident> = keys.iter().map(|&(_, ref i)| i).collect();
+
+      quote! {
+        fn write_value(&self, _: &mut ::rumblebars::SafeWriting) -> ::rumblebars::HBEvalResult { Ok(()) }
+
+        fn typed_node(&self) -> ::rumblebars::preludes::hbdata::HBNodeType<&dyn ::rumblebars::HBData> {
+          ::rumblebars::preludes::hbdata::HBNodeType::Branch(self as &dyn ::rumblebars::HBData)
+        }
+
+        fn get_key(&self, key: &str) -> Option<&dyn ::rumblebars::HBData> {
+          match key {
+            #( #key_strs => Some(&self.#idents as &dyn ::rumblebars::HBData), )*
+            _ => None,
+          }
+        }
+
+        fn as_bool(&self) -> bool { true }
+
+        fn keys<'a>(&'a self) -> ::rumblebars::HBKeysIter<'a> {
+          const KEYS: &'static [&'static str] = &[#( #key_strs2 ),*];
+          Box::new(KEYS.iter().map(|k| *k))
+        }
+
+        fn values<'a>(&'a self) -> ::rumblebars::HBValuesIter<'a> {
+          Box::new(::rumblebars::HBData::iter(self).map(|(_, v)| v))
+        }
+
+        fn iter<'a>(&'a self) -> ::rumblebars::HBIter<'a> {
+          Box::new(::rumblebars::HBData::keys(self).filter_map(move |k| ::rumblebars::HBData::get_key(self, k).map(|v| (k, v))))
+        }
+      }
+    },
+    Fields::Unnamed(ref unnamed) if unnamed.unnamed.len() == 1 => {
+      quote! {
+        fn write_value(&self, out: &mut ::rumblebars::SafeWriting) -> ::rumblebars::HBEvalResult { ::rumblebars::HBData::write_value(&self.0, out) }
+        fn typed_node(&self) -> ::rumblebars::preludes::hbdata::HBNodeType<&dyn ::rumblebars::HBData> { ::rumblebars::HBData::typed_node(&self.0) }
+        fn get_key(&self, key: &str) -> Option<&dyn ::rumblebars::HBData> { ::rumblebars::HBData::get_key(&self.0, key) }
+        fn as_bool(&self) -> bool { ::rumblebars::HBData::as_bool(&self.0) }
+        fn keys<'a>(&'a self) -> ::rumblebars::HBKeysIter<'a> { ::rumblebars::HBData::keys(&self.0) }
+        fn values<'a>(&'a self) -> ::rumblebars::HBValuesIter<'a> { ::rumblebars::HBData::values(&self.0) }
+        fn iter<'a>(&'a self) -> ::rumblebars::HBIter<'a> { ::rumblebars::HBData::iter(&self.0) }
+        fn as_lambda(&self) -> Option<&::rumblebars::Lambda> { ::rumblebars::HBData::as_lambda(&self.0) }
+      }
+    },
+    Fields::Unnamed(ref unnamed) => {
+      let indexes: Vec<Index> = (0..unnamed.unnamed.len()).map(Index::from).collect();
+      let index_strs: Vec<String> = (0..unnamed.unnamed.len()).map(|i| i.to_string()).collect();
+      quote! {
+        fn write_value(&self, out: &mut ::rumblebars::SafeWriting) -> ::rumblebars::HBEvalResult {
+          ::rumblebars::preludes::hbdata::write_values_list(::rumblebars::HBData::values(self), out)
+        }
+
+        fn typed_node(&self) -> ::rumblebars::preludes::hbdata::HBNodeType<&dyn ::rumblebars::HBData> {
+          ::rumblebars::preludes::hbdata::HBNodeType::Array(self as &dyn ::rumblebars::HBData)
+        }
+
+        fn get_key(&self, key: &str) -> Option<&dyn ::rumblebars::HBData> {
+          match key {
+            #( #index_strs => Some(&self.#indexes as &dyn ::rumblebars::HBData), )*
+            _ => None,
+          }
+        }
+
+        fn as_bool(&self) -> bool { true }
+
+        fn keys<'a>(&'a self) -> ::rumblebars::HBKeysIter<'a> { Box::new(None.into_iter()) }
+
+        fn values<'a>(&'a self) -> ::rumblebars::HBValuesIter<'a> {
+          const INDEXES: &'static [&'static str] = &[#( #index_strs ),*];
+          Box::new(INDEXES.iter().filter_map(move |k| ::rumblebars::HBData::get_key(self, k)))
+        }
+
+        fn iter<'a>(&'a self) -> ::rumblebars::HBIter<'a> { Box::new(None.into_iter()) }
+      }
+    },
+    Fields::Unit => {
+      quote! {
+        fn write_value(&self, _: &mut ::rumblebars::SafeWriting) -> ::rumblebars::HBEvalResult { Ok(()) }
+        fn typed_node(&self) -> ::rumblebars::preludes::hbdata::HBNodeType<&dyn ::rumblebars::HBData> {
+          ::rumblebars::preludes::hbdata::HBNodeType::Null
+        }
+        fn get_key(&self, _: &str) -> Option<&dyn ::rumblebars::HBData> { None }
+        fn as_bool(&self) -> bool { false }
+        fn keys<'a>(&'a self) -> ::rumblebars::HBKeysIter<'a> { Box::new(None.into_iter()) }
+        fn values<'a>(&'a self) -> ::rumblebars::HBValuesIter<'a> { Box::new(None.into_iter()) }
+        fn iter<'a>(&'a self) -> ::rumblebars::HBIter<'a> { Box::new(None.into_iter()) }
+      }
+    },
+  })
+}
+
+// per variant match arms for each generated method
+struct VariantArms {
+  write_value: Tokens,
+  typed_node: Tokens,
+  get_key: Tokens,
+  as_bool: Tokens,
+  keys: Tokens,
+  values: Tokens,
+  iter: Tokens,
+  as_lambda: Tokens,
+}
+
+fn expand_enum(name: &Ident, variants: &[(&Ident, String, &Fields)]) -> syn::Result<Tokens> {
+  let mut arms = Vec::new();
+
+  for &(ident, ref variant_name, fields) in variants.iter() {
+    arms.push(match *fields {
+      Fields::Unit => VariantArms {
+        write_value: quote! { #name::#ident => ::rumblebars::HBData::write_value(&#variant_name, out), },
+        typed_node:  quote! { #name::#ident => ::rumblebars::preludes::hbdata::HBNodeType::Leaf(self as &dyn ::rumblebars::HBData), },
+        get_key:     quote! { #name::#ident => None, },
+        as_bool:     quote! { #name::#ident => true, },
+        keys:        quote! { #name::#ident => Box::new(None.into_iter()), },
+        values:      quote! { #name::#ident => Box::new(None.into_iter()), },
+        iter:        quote! { #name::#ident => Box::new(None.into_iter()), },
+        as_lambda:   quote! { #name::#ident => None, },
+      },
+      Fields::Unnamed(ref unnamed) if unnamed.unnamed.len() == 1 => VariantArms {
+        write_value: quote! { #name::#ident(ref v) => ::rumblebars::HBData::write_value(v, out), },
+        typed_node:  quote! { #name::#ident(ref v) => ::rumblebars::HBData::typed_node(v), },
+        get_key:     quote! { #name::#ident(ref v) => ::rumblebars::HBData::get_key(v, key), },
+        as_bool:     quote! { #name::#ident(ref v) => ::rumblebars::HBData::as_bool(v), },
+        keys:        quote! { #name::#ident(ref v) => ::rumblebars::HBData::keys(v), },
+        values:      quote! { #name::#ident(ref v) => ::rumblebars::HBData::values(v), },
+        iter:        quote! { #name::#ident(ref v) => ::rumblebars::HBData::iter(v), },
+        as_lambda:   quote! { #name::#ident(ref v) => ::rumblebars::HBData::as_lambda(v), },
+      },
+      Fields::Unnamed(ref unnamed) => {
+        let len = unnamed.unnamed.len();
+        let bindings: Vec<Ident> = (0..len).map(|i| format_ident!("f{}", i)).collect();
+        let bindings2 = bindings.clone();
+        let index_strs: Vec<String> = (0..len).map(|i| i.to_string()).collect();
+
+        VariantArms {
+          write_value: quote! { #name::#ident(..) => ::rumblebars::preludes::hbdata::write_values_list(::rumblebars::HBData::values(self), out), },
+          typed_node:  quote! { #name::#ident(..) => ::rumblebars::preludes::hbdata::HBNodeType::Array(self as &dyn ::rumblebars::HBData), },
+          get_key:     quote! {
+            #name::#ident(#( ref #bindings ),*) => match key {
+              #( #index_strs => Some(#bindings2 as &dyn ::rumblebars::HBData), )*
+              _ => None,
+            },
+          },
+          as_bool:     quote! { #name::#ident(..) => true, },
+          keys:        quote! { #name::#ident(..) => Box::new(None.into_iter()), },
+          values:      quote! {
+            #name::#ident(..) => {
+              const INDEXES: &'static [&'static str] = &[#( #index_strs ),*];
+              Box::new(INDEXES.iter().filter_map(move |k| ::rumblebars::HBData::get_key(self, k)))
+            },
+          },
+          iter:        quote! { #name::#ident(..) => Box::new(None.into_iter()), },
+          as_lambda:   quote! { #name::#ident(..) => None, },
+        }
+      },
+      Fields::Named(ref named) => {
+        let fields: Vec<&Field> = named.named.iter().collect();
+        let keys = try!(named_keys(&fields));
+        let key_strs: Vec<&String> = keys.iter().map(|&(ref k, _)| k).collect();
+        let key_strs2 = key_strs.clone();
+        let idents: Vec<&Ident> = keys.iter().map(|&(_, ref i)| i).collect();
+        let idents2 = idents.clone();
+
+        VariantArms {
+          write_value: quote! { #name::#ident{..} => Ok(()), },
+          typed_node:  quote! { #name::#ident{..} => ::rumblebars::preludes::hbdata::HBNodeType::Branch(self as &dyn ::rumblebars::HBData), },
+          get_key:     quote! {
+            #name::#ident{ #( ref #idents, )* .. } => match key {
+              #( #key_strs => Some(#idents2 as &dyn ::rumblebars::HBData), )*
+              _ => None,
+            },
+          },
+          as_bool:     quote! { #name::#ident{..} => true, },
+          keys:        quote! {
+            #name::#ident{..} => {
+              const KEYS: &'static [&'static str] = &[#( #key_strs2 ),*];
+              Box::new(KEYS.iter().map(|k| *k))
+            },
+          },
+          values:      quote! { #name::#ident{..} => Box::new(::rumblebars::HBData::iter(self).map(|(_, v)| v)), },
+          iter:        quote! { #name::#ident{..} => Box::new(::rumblebars::HBData::keys(self).filter_map(move |k| ::rumblebars::HBData::get_key(self, k).map(|v| (k, v)))), },
+          as_lambda:   quote! { #name::#ident{..} => None, },
+        }
+      },
+    });
+  }
+
+  let write_value: Vec<&Tokens> = arms.iter().map(|a| &a.write_value).collect();
+  let typed_node:  Vec<&Tokens> = arms.iter().map(|a| &a.typed_node).collect();
+  let get_key:     Vec<&Tokens> = arms.iter().map(|a| &a.get_key).collect();
+  let as_bool:     Vec<&Tokens> = arms.iter().map(|a| &a.as_bool).collect();
+  let keys:        Vec<&Tokens> = arms.iter().map(|a| &a.keys).collect();
+  let values:      Vec<&Tokens> = arms.iter().map(|a| &a.values).collect();
+  let iter:        Vec<&Tokens> = arms.iter().map(|a| &a.iter).collect();
+  let as_lambda:   Vec<&Tokens> = arms.iter().map(|a| &a.as_lambda).collect();
+
+  Ok(quote! {
+    #[allow(unused_variables)]
+    fn write_value(&self, out: &mut ::rumblebars::SafeWriting) -> ::rumblebars::HBEvalResult {
+      match *self { #( #write_value )* }
+    }
+
+    fn typed_node(&self) -> ::rumblebars::preludes::hbdata::HBNodeType<&dyn ::rumblebars::HBData> {
+      match *self { #( #typed_node )* }
+    }
+
+    #[allow(unused_variables)]
+    fn get_key(&self, key: &str) -> Option<&dyn ::rumblebars::HBData> {
+      match *self { #( #get_key )* }
+    }
+
+    fn as_bool(&self) -> bool {
+      match *self { #( #as_bool )* }
+    }
+
+    fn keys<'a>(&'a self) -> ::rumblebars::HBKeysIter<'a> {
+      match *self { #( #keys )* }
+    }
+
+    fn values<'a>(&'a self) -> ::rumblebars::HBValuesIter<'a> {
+      match *self { #( #values )* }
+    }
+
+    fn iter<'a>(&'a self) -> ::rumblebars::HBIter<'a> {
+      match *self { #( #iter )* }
+    }
+
+    fn as_lambda(&self) -> Option<&::rumblebars::Lambda> {
+      match *self { #( #as_lambda )* }
+    }
+  })
+}
+
+#[proc_macro]
+pub fn template(input: TokenStream) -> TokenStream {
+  let source: TemplateSource = match syn::parse(input) {
+    Ok(source) => source,
+    Err(e) => return e.to_compile_error().into(),
+  };
+
+  match expand_template(&source) {
+    Ok(tokens) => tokens.into(),
+    Err(e) => e.to_compile_error().into(),
+  }
+}
+
+// `template!("...")` or `template!(file = "...")`
+enum TemplateSource {
+  Literal(LitStr),
+  File(LitStr),
+}
+
+impl TemplateSource {
+  fn span(&self) -> Span {
+    match *self {
+      TemplateSource::Literal(ref lit) | TemplateSource::File(ref lit) => lit.span(),
+    }
+  }
+}
+
+impl Parse for TemplateSource {
+  fn parse(input: ParseStream) -> syn::Result<TemplateSource> {
+    if input.peek(LitStr) {
+      return Ok(TemplateSource::Literal(try!(input.parse())));
+    }
+
+    let key: Ident = try!(input.parse());
+    if key != "file" {
+      return Err(syn::Error::new_spanned(key, "expected a template string literal or `file = \"path\"`"));
+    }
+    try!(input.parse::<syn::Token![=]>());
+
+    Ok(TemplateSource::File(try!(input.parse())))
+  }
+}
+
+fn expand_template(source: &TemplateSource) -> syn::Result<Tokens> {
+  parse_source(source).map(|(_, expr)| expr)
+}
+
+// parsed template of the source, and the expression building it
+fn parse_source(source: &TemplateSource) -> syn::Result<(rumblebars::Template, Tokens)> {
+  match *source {
+    TemplateSource::Literal(ref lit) => {
+      let template = lit.value();
+      let parsed = try!(rumblebars::parse_detailed(&template).map_err(|failure| {
+        syn::Error::new(literal_span(lit, &template, failure.offset), format!("invalid template, {}", failure))
+      }));
+
+      let expr = try!(template_tokens(&parsed, lit.span()));
+      Ok((parsed, expr))
+    },
+    TemplateSource::File(ref lit) => {
+      let path = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap_or_default()).join(lit.value());
+      let mut template = String::new();
+      try!(fs::File::open(&path).and_then(|mut f| f.read_to_string(&mut template)).map_err(|e| {
+        syn::Error::new(lit.span(), format!("{}: {}", path.display(), e))
+      }));
+      let parsed = try!(rumblebars::parse_detailed(&template).map_err(|failure| {
+        syn::Error::new(lit.span(), format!("{}:{}", path.display(), failure))
+      }));
+
+      let expr = try!(template_tokens(&parsed, lit.span()));
+      let path = path.to_string_lossy().into_owned();
+
+      // include_str! makes the file a dependency of the crate, rebuilt on changes
+      Ok((parsed, quote! {{
+        const _: &'static str = include_str!(#path);
+        #expr
+      }}))
+    },
+  }
+}
+
+fn template_tokens(template: &rumblebars::Template, span: Span) -> syn::Result<Tokens> {
+  let code = try!(rumblebars::template_code(template).map_err(|error| {
+    syn::Error::new(span, format!("template can't be expanded, {}", error))
+  }));
+  code.parse().map_err(|e| {
+    syn::Error::new(Span::call_site(), format!("failed to expand template: {:?}", e))
+  })
+}
+
+// span of the failing expression inside the literal, when the literal has no
+// escapes and the compiler gives sub spans, the whole literal otherwise
+fn literal_span(lit: &LitStr, template: &str, offset: usize) -> Span {
+  let token = lit.token();
+  let repr = token.to_string();
+
+  let start = match repr.find('"') {
+    Some(quote) if repr[quote + 1..].starts_with(template) => quote + 1,
+    _ => return lit.span(),
+  };
+  let end = ::std::cmp::min(offset + 2, template.len());
+
+  token.subspan(start + offset..start + end).unwrap_or_else(|| lit.span())
+}
+
+#[proc_macro]
+pub fn typed_template(input: TokenStream) -> TokenStream {
+  let typed: TypedSource = match syn::parse(input) {
+    Ok(typed) => typed,
+    Err(e) => return e.to_compile_error().into(),
+  };
+
+  match expand_typed_template(&typed) {
+    Ok(tokens) => tokens.into(),
+    Err(e) => e.to_compile_error().into(),
+  }
+}
+
+// `typed_template!(Type, "...")` or `typed_template!(Type, file = "...")`, with
+// helpers called without parameters as `helpers = [name, ...],` after the type
+struct TypedSource {
+  ty: syn::Type,
+  helpers: Vec<String>,
+  source: TemplateSource,
+}
+
+impl Parse for TypedSource {
+  fn parse(input: ParseStream) -> syn::Result<TypedSource> {
+    let ty = try!(input.parse());
+    try!(input.parse::<syn::Token![,]>());
+
+    let mut helpers = Vec::new();
+    if input.peek(Ident) && input.peek2(syn::Token![=]) && input.fork().parse::<Ident>().map(|key| key == "helpers").unwrap_or(false) {
+      try!(input.parse::<Ident>());
+      try!(input.parse::<syn::Token![=]>());
+      let names;
+      syn::bracketed!(names in input);
+      while !names.is_empty() {
+        if names.peek(LitStr) {
+          helpers.push(try!(names.parse::<LitStr>()).value());
+        } else {
+          helpers.push(try!(names.parse::<Ident>()).to_string());
+        }
+        if !names.is_empty() {
+          try!(names.parse::<syn::Token![,]>());
+        }
+      }
+      try!(input.parse::<syn::Token![,]>());
+    }
+
+    Ok(TypedSource { ty: ty, helpers: helpers, source: try!(input.parse()) })
+  }
+}
+
+fn expand_typed_template(typed: &TypedSource) -> syn::Result<Tokens> {
+  let (template, expr) = try!(parse_source(&typed.source));
+  let ty = &typed.ty;
+
+  let checker = Checker { helpers: &typed.helpers, span: typed.source.span() };
+  let mut checks = Vec::new();
+  checker.entries(&template.entries, &mut vec![quote! { #ty }], &mut checks);
+
+  // never called, only there to have the types of paths resolved
+  Ok(quote! {{
+    #[allow(dead_code)]
+    fn __rumblebars_check() {
+      #( #checks )*
+    }
+
+    ::rumblebars::TypedTemplate::<#ty>::new(#expr)
+  }})
+}
+
+struct Checker<'a> {
+  // helpers called without parameters, their names are not paths
+  helpers: &'a [String],
+  span: Span,
+}
+
+impl<'a> Checker<'a> {
+  // statements resolving the type of the paths of `entries`, `scopes` are the
+  // data types of enclosing `each` and `with` blocks
+  fn entries(&self, entries: &rumblebars::Entries, scopes: &mut Vec<Tokens>, checks: &mut Vec<Tokens>) {
+    use rumblebars::HBEntry;
+
+    for entry in entries.iter() {
+      match **entry {
+        HBEntry::Raw(_) | HBEntry::Comment(_) => (),
+        HBEntry::Partial(ref exp) => self.params(exp, scopes, checks),
+        HBEntry::Eval(ref exp) => {
+          let helper = if exp.base.len() == 1 { Some(&exp.base[0][..]) } else { None };
+
+          match (helper, exp.block.as_ref()) {
+            (Some(name), _) if self.helpers.iter().any(|h| h == name) => self.params(exp, scopes, checks),
+            (_, None) if exp.params.is_empty() && exp.options.is_empty() => {
+              checks.extend(self.path_check(&exp.base, scopes));
+            },
+            (Some("if"), _) | (Some("unless"), _) => {
+              self.params(exp, scopes, checks);
+              for block in exp.block.iter().chain(exp.else_block.iter()) {
+                self.entries(block, scopes, checks);
+              }
+            },
+            (Some(helper @ "each"), Some(block)) | (Some(helper @ "with"), Some(block)) => {
+              self.params(exp, scopes, checks);
+              let data = match exp.params.first() {
+                Some(&rumblebars::HBValHolder::Path(ref path)) => path_type(path, scopes),
+                _ => None,
+              };
+
+              if let Some(data) = data {
+                scopes.push(if helper == "each" { quote! { <#data as ::rumblebars::HBItems>::Item } } else { data });
+                self.entries(block, scopes, checks);
+                scopes.pop();
+              }
+
+              for block in exp.else_block.iter() {
+                self.entries(block, scopes, checks);
+              }
+            },
+            // sections and other helpers may change the data of their blocks
+            (_, Some(_)) if exp.params.is_empty() && exp.options.is_empty() => {
+              checks.extend(self.path_check(&exp.base, scopes));
+            },
+            _ => self.params(exp, scopes, checks),
+          }
+        },
+      }
+    }
+  }
+
+  // paths given as parameters and options
+  fn params(&self, exp: &rumblebars::HBExpression, scopes: &[Tokens], checks: &mut Vec<Tokens>) {
+    let values = exp.params.iter().chain(exp.options.iter().map(|&(_, ref v)| v));
+
+    for value in values {
+      if let rumblebars::HBValHolder::Path(ref path) = *value {
+        checks.extend(self.path_check(path, scopes));
+      }
+    }
+  }
+
+  fn path_check(&self, path: &[String], scopes: &[Tokens]) -> Option<Tokens> {
+    let span = self.span;
+    path_type(path, scopes).map(|ty| quote_spanned! {span=> let _: ::std::marker::PhantomData<#ty> = ::std::marker::PhantomData; })
+  }
+}
+
+// type of the value at `path`, `None` for globals, that can't be checked
+fn path_type(path: &[String], scopes: &[Tokens]) -> Option<Tokens> {
+  let mut depth = scopes.len() - 1;
+  let mut ty: Option<Tokens> = None;
+
+  for key in path.iter() {
+    let base = match ty {
+      Some(ref ty) => ty.clone(),
+      None => match &key[..] {
+        "." => continue,
+        ".." => { depth = depth.saturating_sub(1); continue },
+        "@root" => { depth = 0; continue },
+        _ if key.starts_with("@") => return None,
+        _ => scopes[depth].clone(),
+      },
+    };
+
+    ty = Some(match key.parse::<usize>() {
+      Ok(_) => quote! { <#base as ::rumblebars::HBItems>::Item },
+      Err(_) => {
+        let key = key_type(key);
+        quote! { <#base as ::rumblebars::HBField<#key>>::Value }
+      },
+    });
+  }
+
+  Some(ty.unwrap_or_else(|| scopes[depth].clone()))
+}
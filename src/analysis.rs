@@ -0,0 +1,201 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use parse::{Entries, HBExpression};
+use eval::EvalContext;
+use printer::{partial_source, path_source, value_source};
+use visit::Visitor;
+
+/// What a template references, through the partials it includes
+#[derive(Debug, Default)]
+pub struct Analysis {
+  /// data paths read, see `PathUsage`
+  pub paths: BTreeSet<PathUsage>,
+  /// helpers called, whether registered or not
+  pub helpers: BTreeSet<String>,
+  /// partials included, as `shared/header`
+  pub partials: BTreeSet<String>,
+  /// partials included but not registered
+  pub missing_partials: BTreeSet<String>,
+}
+
+/// Data path read by a template, with the blocks that change the context
+/// it is read in.
+///
+/// Scopes are the tags of enclosing blocks, outermost first, as `each items`,
+/// `with author`, `comments` for sections, or `> item .` for partials given a
+/// context. `if`, `unless`, inverted sections and `{{else}}` blocks keep the
+/// context of their parent, and are not scopes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathUsage {
+  /// keys as written, `..` and `@root` included
+  pub path: Vec<String>,
+  pub scope: Vec<String>,
+}
+
+impl PathUsage {
+  /// same path, with leading `.`, `..` and `@root` applied to its scope
+  ///
+  /// ```
+  /// use rumblebars::{parse, EvalContext, PathUsage};
+  ///
+  /// let template = parse("{{#each posts}}{{../site.title}}{{/each}}").unwrap();
+  /// let analysis = template.analyze(&EvalContext::new());
+  /// let resolved: Vec<_> = analysis.paths.iter().map(PathUsage::resolved).map(|p| p.to_string()).collect();
+  ///
+  /// assert_eq!(resolved, vec!["site.title", "posts"]);
+  /// ```
+  pub fn resolved(&self) -> PathUsage {
+    let mut scope = self.scope.clone();
+    let mut keys = &self.path[..];
+
+    while let Some(key) = keys.first() {
+      match &key[..] {
+        "." => (),
+        ".." => { scope.pop(); },
+        "@root" => scope.clear(),
+        _ => break,
+      }
+      keys = &keys[1..];
+    }
+
+    PathUsage {
+      path: if keys.is_empty() { vec![".".to_string()] } else { keys.to_vec() },
+      scope: scope,
+    }
+  }
+}
+
+/// `path` or `path in scope, scope`
+impl fmt::Display for PathUsage {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    try!(write!(f, "{}", path_source(&self.path)));
+    if !self.scope.is_empty() {
+      try!(write!(f, " in {}", self.scope.join(", ")));
+    }
+    Ok(())
+  }
+}
+
+/// Data paths, helpers and partials referenced by `entries`, partials
+/// registered in `eval_context` are analyzed where they are included.
+///
+/// Expressions are helper calls when a helper of that name is registered, or
+/// when they are given parameters or options. `@index` like variables are not
+/// data paths.
+///
+/// ```
+/// use rumblebars::{parse, analyze, EvalContext};
+///
+/// let mut context = EvalContext::new();
+/// context.register_partial("author".to_string(), parse("{{name}} {{> avatar}}").unwrap());
+///
+/// let template = parse("{{title}}{{#with post}}{{> author author}}{{/with}}").unwrap();
+/// let analysis = analyze(&template.entries, &context);
+///
+/// let paths: Vec<_> = analysis.paths.iter().map(|p| p.to_string()).collect();
+/// assert_eq!(paths, vec!["author in with post", "name in with post, > author author", "post", "title"]);
+/// assert!(analysis.helpers.contains("with"));
+/// assert!(analysis.partials.contains("author") && analysis.missing_partials.contains("avatar"));
+/// ```
+pub fn analyze(entries: &Entries, eval_context: &EvalContext) -> Analysis {
+  let mut analyzer = Analyzer {
+    context: eval_context,
+    analysis: Analysis::default(),
+    scope: Vec::new(),
+    partials: Vec::new(),
+  };
+
+  analyzer.visit_entries(entries);
+  analyzer.analysis
+}
+
+struct Analyzer<'a> {
+  context: &'a EvalContext,
+  analysis: Analysis,
+  scope: Vec<String>,
+  // partials being analyzed, recursive ones are analyzed once
+  partials: Vec<String>,
+}
+
+impl<'a> Analyzer<'a> {
+  fn values(&mut self, exp: &HBExpression) {
+    for param in exp.params.iter() {
+      self.visit_value(param);
+    }
+    for &(_, ref value) in exp.options.iter() {
+      self.visit_value(value);
+    }
+  }
+}
+
+// tag content, as `each items`
+pub fn scope_label(prefix: &str, exp: &HBExpression, partial: bool) -> String {
+  let name = if partial { partial_source(&exp.partial_name()) } else { path_source(&exp.base) };
+  let mut label = format!("{}{}", prefix, name);
+  for param in exp.params.iter() {
+    label.push(' ');
+    label.push_str(&value_source(param));
+  }
+  label
+}
+
+impl<'a> Visitor for Analyzer<'a> {
+  fn visit_expression(&mut self, exp: &HBExpression) {
+    let registered = exp.base.len() == 1 && self.context.has_helper_with_name(&exp.base[0]);
+    let helper = registered || !exp.params.is_empty() || !exp.options.is_empty();
+
+    if helper {
+      self.analysis.helpers.insert(exp.path());
+    } else {
+      self.visit_path(&exp.base);
+    }
+    self.values(exp);
+
+    let keeps_context = exp.render_options.inverse || helper && (exp.base == ["if"] || exp.base == ["unless"]);
+    if let Some(ref block) = exp.block {
+      if !keeps_context {
+        self.scope.push(scope_label("", exp, false));
+      }
+      self.visit_entries(block);
+      if !keeps_context {
+        self.scope.pop();
+      }
+    }
+    if let Some(ref else_block) = exp.else_block {
+      self.visit_entries(else_block);
+    }
+  }
+
+  fn visit_partial(&mut self, exp: &HBExpression) {
+    let name = exp.partial_name();
+    self.analysis.partials.insert(name.clone());
+    self.values(exp);
+
+    let context = self.context;
+    match context.partial_with_name(&name) {
+      Some(template) => if !self.partials.contains(&name) {
+        let scoped = !exp.params.is_empty();
+        if scoped {
+          self.scope.push(scope_label("> ", exp, true));
+        }
+        self.partials.push(name);
+        self.visit_entries(&template.entries);
+        self.partials.pop();
+        if scoped {
+          self.scope.pop();
+        }
+      },
+      None => { self.analysis.missing_partials.insert(name); },
+    }
+  }
+
+  fn visit_path(&mut self, path: &[String]) {
+    match path.first() {
+      Some(key) if key.starts_with("@") && key != "@root" => (),
+      _ => {
+        self.analysis.paths.insert(PathUsage { path: path.to_vec(), scope: self.scope.clone() });
+      },
+    }
+  }
+}
@@ -0,0 +1,441 @@
+use std::io;
+use std::borrow::Cow;
+use serialize::json::Json;
+
+use parse::{parse, Entries, HBEntry, ParseError, ParseResult};
+use escape::{Escaper, HTMLEscaper, JavascriptEscaper, URLEscaper};
+
+/// Position of an expression in the surrounding html, used by contextual
+/// autoescaping to choose how values are escaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
+pub enum HTMLContext {
+  /// element content
+  Text,
+  /// inside a tag, outside of attributes values
+  Tag(Element),
+  /// after an attribute name
+  AttrName(Element, AttrKind),
+  /// after an attribute `=`, before its value
+  BeforeValue(Element, AttrKind),
+  /// attribute value
+  AttrValue(Element, AttrKind, Delim),
+  /// `<script>` element content
+  Script(JSState),
+  /// `<style>` element content
+  Style,
+  /// html comment
+  Comment,
+}
+
+/// elements which content is not html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
+pub enum Element {
+  Normal,
+  Script,
+  Style,
+}
+
+/// attribute value language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
+pub enum AttrKind {
+  Normal,
+  URL(URLPart),
+  Script(JSState),
+  Style,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
+pub enum URLPart {
+  /// nothing written yet, scheme is checked
+  Start,
+  /// path
+  PreQuery,
+  /// after `?` or `#`
+  QueryOrFragment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
+pub enum Delim {
+  Double,
+  Single,
+  Unquoted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
+pub enum JSState {
+  /// where a javascript value is expected
+  Value,
+  DoubleQuoted,
+  SingleQuoted,
+}
+
+/// replacement of values that cannot be made safe in their context
+pub static UNSAFE_REPLACEMENT: &'static str = "ZgotmplZ";
+
+static URL_ATTRIBUTES: &'static [&'static str] = &[
+  "action", "background", "cite", "codebase", "data", "formaction", "href", "icon",
+  "longdesc", "manifest", "poster", "profile", "src", "usemap", "xmlns",
+];
+
+/// Parses a template for contextual html autoescaping.
+///
+/// Each expression is escaped for where it sits in the surrounding html, in the
+/// spirit of Go's `html/template`: element content and attributes are html escaped,
+/// `href` like attributes only accept http(s) and mailto urls, values written in
+/// scripts (`<script>`, `on*` attributes) become javascript strings, and style values
+/// are restricted to plain css values. Values that cannot be made safe are rendered as
+/// `ZgotmplZ`. `{{{expression}}}` is still written raw.
+///
+/// As context must be known for every expression, blocks have to end in the context
+/// they started in. Partials are rendered in their own context, parse them with this
+/// function too so that they are escaped as well (as html content).
+///
+/// # Examples
+///
+/// ```
+/// extern crate rustc_serialize as serialize;
+/// extern crate rumblebars;
+/// # fn main() {
+/// use serialize::json::Json;
+///
+/// let template = rumblebars::parse_contextual(r##"<a href="{{url}}" onclick="go({{id}})">{{name}}</a>"##).unwrap();
+/// let data = Json::from_str(r##"{"url": "javascript:alert(1)", "id": "x'); alert('", "name": "<b>"}"##).unwrap();
+///
+/// assert_eq!(
+///   template.eval_to_string(&data).unwrap(),
+///   r##"<a href="#ZgotmplZ" onclick="go(&quot;x\&#x27;); alert(\&#x27;&quot;)">&lt;b&gt;</a>"##
+/// );
+/// # }
+/// ```
+pub fn parse_contextual(template: &str) -> ParseResult {
+  let mut parsed = try!(parse(template));
+  try!(annotate(&mut parsed.entries, HTMLContext::Text));
+  Ok(parsed)
+}
+
+// sets the context of expressions, returns context at the end of entries
+fn annotate(entries: &mut Entries, start: HTMLContext) -> Result<HTMLContext, (ParseError, Option<String>)> {
+  let mut context = start;
+
+  for entry in entries.iter_mut() {
+    match **entry {
+      HBEntry::Raw(ref s) => {
+        context = transition(context, s);
+      },
+      HBEntry::Partial(_) => (), // partials are expected to leave context unchanged
+      HBEntry::Comment(_) => (),
+      HBEntry::Eval(ref mut exp) => {
+        if exp.block.is_some() || exp.else_block.is_some() {
+          let path = exp.path();
+          for block in exp.block.iter_mut().chain(exp.else_block.iter_mut()) {
+            let end = try!(annotate(block, context));
+            if end != context {
+              return Err((
+                ParseError::AmbiguousContext,
+                Some(format!("‘{}’ block starts in {:?} context but ends in {:?} context", path, context, end))
+              ));
+            }
+          }
+        } else {
+          exp.render_options.context = Some(context);
+          context = after_expression(context);
+        }
+      },
+    }
+  }
+
+  Ok(context)
+}
+
+// an expression output is content, move on if context expected some
+fn after_expression(context: HTMLContext) -> HTMLContext {
+  match context {
+    HTMLContext::BeforeValue(e, AttrKind::URL(_)) |
+    HTMLContext::AttrValue(e, AttrKind::URL(URLPart::Start), Delim::Unquoted) => HTMLContext::AttrValue(e, AttrKind::URL(URLPart::PreQuery), Delim::Unquoted),
+    HTMLContext::AttrValue(e, AttrKind::URL(URLPart::Start), d) => HTMLContext::AttrValue(e, AttrKind::URL(URLPart::PreQuery), d),
+    HTMLContext::BeforeValue(e, k) => HTMLContext::AttrValue(e, k, Delim::Unquoted),
+    c => c,
+  }
+}
+
+/// context at the end of raw text starting in `context`
+pub fn transition(context: HTMLContext, raw: &str) -> HTMLContext {
+  let mut context = context;
+  let mut rest = raw;
+
+  while !rest.is_empty() {
+    let (next, consumed) = step(context, rest);
+    context = next;
+    rest = &rest[consumed..];
+  }
+
+  context
+}
+
+fn starts_with_ignore_case(s: &str, prefix: &str) -> bool {
+  s.len() >= prefix.len() && s.is_char_boundary(prefix.len()) && s[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+fn is_html_space(c: char) -> bool {
+  match c { ' ' | '\t' | '\n' | '\r' | '\u{c}' => true, _ => false }
+}
+
+fn is_name_char(c: char) -> bool {
+  !is_html_space(c) && c != '=' && c != '>' && c != '/' && c != '"' && c != '\''
+}
+
+fn element_content(element: Element) -> HTMLContext {
+  match element {
+    Element::Normal => HTMLContext::Text,
+    Element::Script => HTMLContext::Script(JSState::Value),
+    Element::Style  => HTMLContext::Style,
+  }
+}
+
+fn attribute_kind(name: &str) -> AttrKind {
+  let name = name.to_lowercase();
+  // namespaced attributes (xlink:href) are checked by local name
+  let local = name.rsplit(':').next().unwrap_or("");
+
+  if local.starts_with("on") {
+    AttrKind::Script(JSState::Value)
+  } else if local == "style" {
+    AttrKind::Style
+  } else if URL_ATTRIBUTES.contains(&local) {
+    AttrKind::URL(URLPart::Start)
+  } else {
+    AttrKind::Normal
+  }
+}
+
+// javascript string tracking, returns new state and consumed length
+fn js_step(state: JSState, rest: &str) -> (JSState, usize) {
+  let c = rest.chars().next().unwrap();
+  match (state, c) {
+    (JSState::Value, '"')         => (JSState::DoubleQuoted, 1),
+    (JSState::Value, '\'')        => (JSState::SingleQuoted, 1),
+    (JSState::DoubleQuoted, '"')  => (JSState::Value, 1),
+    (JSState::SingleQuoted, '\'') => (JSState::Value, 1),
+    (JSState::DoubleQuoted, '\\') | (JSState::SingleQuoted, '\\') => {
+      // skip escaped character
+      (state, rest.chars().take(2).map(|c| c.len_utf8()).fold(0, |a, l| a + l))
+    },
+    (s, c) => (s, c.len_utf8()),
+  }
+}
+
+// one step of the html state machine: new context and consumed length, that
+// may be 0 when only the context changes
+fn step(context: HTMLContext, rest: &str) -> (HTMLContext, usize) {
+  let c = rest.chars().next().unwrap();
+
+  match context {
+    HTMLContext::Text => {
+      match rest.find('<') {
+        None => (context, rest.len()),
+        Some(i) if i > 0 => (context, i),
+        Some(_) => {
+          if rest.starts_with("<!--") {
+            return (HTMLContext::Comment, 4);
+          }
+
+          let (is_end, name_start) = if rest.starts_with("</") { (true, 2) } else { (false, 1) };
+          match rest[name_start..].chars().next() {
+            Some(n) if n.is_alphabetic() => {
+              let name_len = rest[name_start..].find(|c: char| !c.is_alphanumeric() && c != '-').unwrap_or(rest.len() - name_start);
+              let name = rest[name_start..name_start + name_len].to_lowercase();
+              let element = match (&name[..], is_end) {
+                ("script", false) => Element::Script,
+                ("style", false)  => Element::Style,
+                _ => Element::Normal,
+              };
+              (HTMLContext::Tag(element), name_start + name_len)
+            },
+            _ => (context, 1),
+          }
+        }
+      }
+    },
+    HTMLContext::Comment => {
+      match rest.find("-->") {
+        Some(i) => (HTMLContext::Text, i + 3),
+        None => (context, rest.len()),
+      }
+    },
+    HTMLContext::Tag(element) => {
+      match c {
+        '>' => (element_content(element), 1),
+        '/' => (context, 1),
+        c if is_html_space(c) => (context, 1),
+        _ => {
+          let name_len = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+          let name_len = if name_len == 0 { c.len_utf8() } else { name_len };
+          (HTMLContext::AttrName(element, attribute_kind(&rest[..name_len])), name_len)
+        },
+      }
+    },
+    HTMLContext::AttrName(element, kind) => {
+      match c {
+        '=' => (HTMLContext::BeforeValue(element, kind), 1),
+        c if is_html_space(c) => (context, 1),
+        _ => (HTMLContext::Tag(element), 0),
+      }
+    },
+    HTMLContext::BeforeValue(element, kind) => {
+      match c {
+        '"'  => (HTMLContext::AttrValue(element, kind, Delim::Double), 1),
+        '\'' => (HTMLContext::AttrValue(element, kind, Delim::Single), 1),
+        '>'  => (element_content(element), 1),
+        c if is_html_space(c) => (context, 1),
+        _ => (HTMLContext::AttrValue(element, kind, Delim::Unquoted), 0),
+      }
+    },
+    HTMLContext::AttrValue(element, kind, delim) => {
+      match (delim, c) {
+        (Delim::Double, '"') | (Delim::Single, '\'') => (HTMLContext::Tag(element), 1),
+        (Delim::Unquoted, '>') => (element_content(element), 1),
+        (Delim::Unquoted, c) if is_html_space(c) => (HTMLContext::Tag(element), 1),
+        _ => {
+          match kind {
+            AttrKind::URL(part) => {
+              let part = match (part, c) {
+                (_, '?') | (_, '#') => URLPart::QueryOrFragment,
+                (URLPart::Start, _) => URLPart::PreQuery,
+                (p, _) => p,
+              };
+              (HTMLContext::AttrValue(element, AttrKind::URL(part), delim), c.len_utf8())
+            },
+            AttrKind::Script(js) => {
+              let (js, consumed) = js_step(js, rest);
+              (HTMLContext::AttrValue(element, AttrKind::Script(js), delim), consumed)
+            },
+            _ => (context, c.len_utf8()),
+          }
+        },
+      }
+    },
+    HTMLContext::Script(js) => {
+      if starts_with_ignore_case(rest, "</script") {
+        (HTMLContext::Text, 0)
+      } else {
+        let (js, consumed) = js_step(js, rest);
+        (HTMLContext::Script(js), consumed)
+      }
+    },
+    HTMLContext::Style => {
+      if starts_with_ignore_case(rest, "</style") {
+        (HTMLContext::Text, 0)
+      } else {
+        (context, c.len_utf8())
+      }
+    },
+  }
+}
+
+// html escaping of unquoted attribute values, where spaces end the value
+struct UnquotedAttrEscaper;
+
+impl Escaper for UnquotedAttrEscaper {
+  fn escape_char(&self, c: char) -> Option<Cow<'static, str>> {
+    match c {
+      '=' => Some(Cow::Borrowed("&#61;")),
+      c if is_html_space(c) => Some(Cow::Owned(format!("&#{};", c as u32))),
+      c => HTMLEscaper.escape_char(c),
+    }
+  }
+}
+
+// percent encodes characters that are never valid in urls, keeping url structure
+struct URLNormalizer;
+
+impl Escaper for URLNormalizer {
+  fn escape_char(&self, c: char) -> Option<Cow<'static, str>> {
+    match c {
+      'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '~' | '%' |
+      '!' | '#' | '$' | '&' | '\'' | '(' | ')' | '*' | '+' | ',' | '/' | ':' | ';' | '=' | '?' | '@' | '[' | ']' => None,
+      c => URLEscaper.escape_char(c),
+    }
+  }
+}
+
+fn escaped_string(escaper: &Escaper, value: &str) -> String {
+  let mut buf = Vec::new();
+  escaper.escape(value, &mut buf).ok();
+  String::from_utf8(buf).unwrap_or(String::new())
+}
+
+fn is_safe_scheme(url: &str) -> bool {
+  match url.find(|c| c == ':' || c == '/' || c == '?' || c == '#') {
+    Some(i) if url[i..].starts_with(':') => {
+      let scheme = url[..i].to_lowercase();
+      scheme == "http" || scheme == "https" || scheme == "mailto"
+    },
+    _ => true, // relative url
+  }
+}
+
+fn url_value(part: URLPart, value: &str) -> String {
+  match part {
+    URLPart::Start if !is_safe_scheme(value) => format!("#{}", UNSAFE_REPLACEMENT),
+    URLPart::Start | URLPart::PreQuery => escaped_string(&URLNormalizer, value),
+    URLPart::QueryOrFragment => escaped_string(&URLEscaper, value),
+  }
+}
+
+fn js_value(state: JSState, value: &str) -> String {
+  match state {
+    JSState::Value => {
+      // numbers and booleans are kept as is, anything else becomes a string
+      match Json::from_str(value) {
+        Ok(Json::I64(_)) | Ok(Json::U64(_)) | Ok(Json::F64(_)) | Ok(Json::Boolean(_)) | Ok(Json::Null) => value.to_string(),
+        _ => format!("\"{}\"", escaped_string(&JavascriptEscaper, value)),
+      }
+    },
+    JSState::DoubleQuoted | JSState::SingleQuoted => escaped_string(&JavascriptEscaper, value),
+  }
+}
+
+fn css_value(value: &str) -> String {
+  let safe = value.chars().all(|c| match c {
+    'a'..='z' | 'A'..='Z' | '0'..='9' | ' ' | '#' | '%' | '.' | ',' | '_' | '-' => true,
+    _ => false,
+  });
+
+  if safe { value.to_string() } else { UNSAFE_REPLACEMENT.to_string() }
+}
+
+fn attr_value(kind: AttrKind, value: &str) -> String {
+  match kind {
+    AttrKind::Normal => value.to_string(),
+    AttrKind::URL(part) => url_value(part, value),
+    AttrKind::Script(js) => js_value(js, value),
+    AttrKind::Style => css_value(value),
+  }
+}
+
+/// writes an expression value escaped for its context
+pub fn escape_in_context(context: HTMLContext, value: &str, out: &mut io::Write) -> io::Result<()> {
+  match context {
+    HTMLContext::Text => HTMLEscaper.escape(value, out),
+    HTMLContext::Comment => Ok(()),
+    HTMLContext::Tag(_) | HTMLContext::AttrName(..) => {
+      // only plain attribute names are accepted
+      if !value.is_empty() && value.chars().all(|c| match c { 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | ':' => true, _ => false }) {
+        out.write_all(value.as_bytes())
+      } else {
+        out.write_all(UNSAFE_REPLACEMENT.as_bytes())
+      }
+    },
+    HTMLContext::BeforeValue(_, kind) => UnquotedAttrEscaper.escape(&attr_value(kind, value), out),
+    HTMLContext::AttrValue(_, kind, Delim::Unquoted) => UnquotedAttrEscaper.escape(&attr_value(kind, value), out),
+    HTMLContext::AttrValue(_, kind, _) => HTMLEscaper.escape(&attr_value(kind, value), out),
+    HTMLContext::Script(js) => out.write_all(js_value(js, value).as_bytes()),
+    HTMLContext::Style => out.write_all(css_value(value).as_bytes()),
+  }
+}
@@ -0,0 +1,331 @@
+//! `hbs`, command line tools for handlebars templates
+//!
+//! ```text
+//! hbs fmt [--check | --write] [--indent <n>] [<file>...]
+//! hbs lint [--json] [--extension <ext>] [--page <name>...] <dir>
+//! hbs graph [--extension <ext>] [--allow-recursion <name>...] <dir>
+//! hbs check [--extension <ext>] <dir> <template> <data.json>
+//! ```
+//!
+//! `fmt` prints formatted templates, standard input is formatted when no file is
+//! given. With `--write` files are formatted in place, with `--check` files that
+//! are not formatted are listed and the exit status is 1.
+//!
+//! `lint` checks the templates of a directory, registered as partials under
+//! their relative path. Partials that the `--page` templates never include are
+//! reported as unused. Lints are printed one per line, or as a json array with
+//! `--json`, the exit status is 1 when one of them is an error.
+//!
+//! `graph` prints the include graph of the templates of a directory in
+//! Graphviz format. Include cycles and missing partials are reported on the
+//! error output, cycles exit with status 1 unless guarded by a block and
+//! allowed with `--allow-recursion`.
+//!
+//! `check` validates a json document against the template `template` of a
+//! directory: paths the template reads that the data misses, with the
+//! template, line and column reading them, and data paths the template never
+//! reads, are listed. Missing paths exit with status 1.
+//!
+//! Errors exit with status 2.
+
+extern crate rumblebars;
+extern crate rustc_serialize;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process;
+
+use rustc_serialize::json::{Json, ToJson};
+
+use rumblebars::{Formatter, FormatError, EvalContext, Linter, Severity, TemplateLoader, PartialGraph, parse};
+
+const USAGE: &'static str = "usage: hbs fmt [--check | --write] [--indent <n>] [<file>...]
+       hbs lint [--json] [--extension <ext>] [--page <name>...] <dir>
+       hbs graph [--extension <ext>] [--allow-recursion <name>...] <dir>
+       hbs check [--extension <ext>] <dir> <template> <data.json>";
+
+#[derive(PartialEq)]
+enum Mode {
+  Print,
+  Check,
+  Write,
+}
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+
+  let status = match args.first().map(|s| &s[..]) {
+    Some("fmt") => fmt(&args[1..]),
+    Some("lint") => lint(&args[1..]),
+    Some("graph") => graph(&args[1..]),
+    Some("check") => check(&args[1..]),
+    _ => {
+      let _ = writeln!(io::stderr(), "{}", USAGE);
+      2
+    },
+  };
+
+  process::exit(status);
+}
+
+fn fmt(args: &[String]) -> i32 {
+  let mut mode = Mode::Print;
+  let mut formatter = Formatter::new();
+  let mut files = Vec::new();
+
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match &arg[..] {
+      "--check" => mode = Mode::Check,
+      "--write" => mode = Mode::Write,
+      "--indent" => {
+        match args.next().and_then(|n| n.parse::<usize>().ok()) {
+          Some(n) => formatter = formatter.indent(&::std::iter::repeat(" ").take(n).collect::<String>()),
+          None => return usage_error("--indent expects a number of spaces"),
+        }
+      },
+      _ if arg.starts_with("--") => return usage_error(&format!("unknown option {}", arg)),
+      _ => files.push(arg.clone()),
+    }
+  }
+
+  if files.is_empty() {
+    if mode == Mode::Write {
+      return usage_error("--write needs files");
+    }
+    let mut source = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut source) {
+      return error(&format!("<stdin>: {}", e));
+    }
+    return format_source("<stdin>", &source, &formatter, &mode, |formatted| {
+      io::stdout().write_all(formatted.as_bytes())
+    });
+  }
+
+  let mut status = 0;
+  for file in files.iter() {
+    let mut source = String::new();
+    if let Err(e) = fs::File::open(file).and_then(|mut f| f.read_to_string(&mut source)) {
+      status = error(&format!("{}: {}", file, e));
+      continue;
+    }
+
+    let file_status = format_source(file, &source, &formatter, &mode, |formatted| {
+      match mode {
+        Mode::Write if formatted != source => fs::File::create(file).and_then(|mut f| f.write_all(formatted.as_bytes())),
+        Mode::Write => Ok(()),
+        _ => io::stdout().write_all(formatted.as_bytes()),
+      }
+    });
+    status = ::std::cmp::max(status, file_status);
+  }
+
+  status
+}
+
+// formats `source` and gives the result to `output`, unless checking
+fn format_source<F>(name: &str, source: &str, formatter: &Formatter, mode: &Mode, output: F) -> i32 where F: FnOnce(&str) -> io::Result<()> {
+  match formatter.format(source) {
+    Ok(ref formatted) if *mode == Mode::Check => {
+      if formatted != source {
+        println!("{}", name);
+        1
+      } else {
+        0
+      }
+    },
+    Ok(formatted) => match output(&formatted) {
+      Ok(()) => 0,
+      Err(e) => error(&format!("{}: {}", name, e)),
+    },
+    // parse errors start with their position
+    Err(FormatError::Parse(failure)) => error(&format!("{}:{}", name, failure)),
+    Err(e) => error(&format!("{}: {}", name, e)),
+  }
+}
+
+fn lint(args: &[String]) -> i32 {
+  let mut json = false;
+  let mut loader = TemplateLoader::new();
+  let mut pages = Vec::new();
+  let mut dir = None;
+
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match &arg[..] {
+      "--json" => json = true,
+      "--extension" => match args.next() {
+        Some(ext) => loader = loader.extension(ext),
+        None => return usage_error("--extension expects an extension"),
+      },
+      "--page" => match args.next() {
+        Some(page) => pages.push(&page[..]),
+        None => return usage_error("--page expects a template name"),
+      },
+      _ if arg.starts_with("--") => return usage_error(&format!("unknown option {}", arg)),
+      _ if dir.is_none() => dir = Some(arg),
+      _ => return usage_error("lint expects a single directory"),
+    }
+  }
+
+  let dir = match dir {
+    Some(dir) => Path::new(dir),
+    None => return usage_error("lint needs a directory"),
+  };
+  let sources = match loader.load_sources(dir) {
+    Ok(sources) => sources,
+    Err(errors) => {
+      for e in errors.iter() {
+        error(&e.to_string());
+      }
+      return 2;
+    },
+  };
+
+  let mut context = EvalContext::new();
+  for &(ref name, ref source) in sources.iter() {
+    if let Ok(template) = parse(source) {
+      context.register_partial(name.clone(), template);
+    }
+  }
+
+  let linter = Linter::new();
+  let mut lints = match linter.lint_templates(&sources, &context) {
+    Ok(lints) => lints,
+    Err(errors) => {
+      for e in errors.iter() {
+        error(&e.to_string());
+      }
+      return 2;
+    },
+  };
+  if !pages.is_empty() {
+    lints.extend(linter.unused_partials(&pages, &context));
+  }
+
+  if json {
+    println!("{}", lints.to_json());
+  } else {
+    for lint in lints.iter() {
+      println!("{}", lint);
+    }
+  }
+
+  if lints.iter().any(|lint| lint.severity == Severity::Error) { 1 } else { 0 }
+}
+
+fn graph(args: &[String]) -> i32 {
+  let mut loader = TemplateLoader::new();
+  let mut allowed = Vec::new();
+  let mut dir = None;
+
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match &arg[..] {
+      "--extension" => match args.next() {
+        Some(ext) => loader = loader.extension(ext),
+        None => return usage_error("--extension expects an extension"),
+      },
+      "--allow-recursion" => match args.next() {
+        Some(name) => allowed.push(name),
+        None => return usage_error("--allow-recursion expects a template name"),
+      },
+      _ if arg.starts_with("--") => return usage_error(&format!("unknown option {}", arg)),
+      _ if dir.is_none() => dir = Some(arg),
+      _ => return usage_error("graph expects a single directory"),
+    }
+  }
+
+  let dir = match dir {
+    Some(dir) => Path::new(dir),
+    None => return usage_error("graph needs a directory"),
+  };
+  let mut context = EvalContext::new();
+  if let Err(errors) = loader.register_partials(dir, &mut context) {
+    for e in errors.iter() {
+      error(&e.to_string());
+    }
+    return 2;
+  }
+
+  let graph = allowed.iter().fold(PartialGraph::new(&context), |graph, name| graph.allow_recursion(name));
+  print!("{}", graph.to_dot());
+
+  for (name, target) in graph.missing() {
+    let _ = writeln!(io::stderr(), "{}: partial `{}` is not registered", name, target);
+  }
+  let cycles = graph.cycles();
+  for cycle in cycles.iter() {
+    let _ = writeln!(io::stderr(), "include cycle{}: {}", if cycle.guarded { " guarded by a block" } else { "" }, cycle.templates.join(", "));
+  }
+
+  if cycles.is_empty() { 0 } else { 1 }
+}
+
+fn check(args: &[String]) -> i32 {
+  let mut loader = TemplateLoader::new();
+  let mut operands = Vec::new();
+
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match &arg[..] {
+      "--extension" => match args.next() {
+        Some(ext) => loader = loader.extension(ext),
+        None => return usage_error("--extension expects an extension"),
+      },
+      _ if arg.starts_with("--") => return usage_error(&format!("unknown option {}", arg)),
+      _ => operands.push(&arg[..]),
+    }
+  }
+
+  if operands.len() != 3 {
+    return usage_error("check expects a directory, a template name and a data file");
+  }
+  let (dir, name, data_file) = (operands[0], operands[1], operands[2]);
+
+  let mut context = EvalContext::new();
+  if let Err(errors) = loader.register_partials(Path::new(dir), &mut context) {
+    for e in errors.iter() {
+      error(&e.to_string());
+    }
+    return 2;
+  }
+
+  let mut source = String::new();
+  if let Err(e) = fs::File::open(data_file).and_then(|mut f| f.read_to_string(&mut source)) {
+    return error(&format!("{}: {}", data_file, e));
+  }
+  let data = match Json::from_str(&source) {
+    Ok(data) => data,
+    Err(e) => return error(&format!("{}: {}", data_file, e)),
+  };
+
+  let report = match context.partial_with_name(name) {
+    Some(template) => template.check_data(&data, &context),
+    None => return error(&format!("no template named {}", name)),
+  };
+
+  // positions are in the source of the partial reading the path
+  for missing in report.missing.iter() {
+    let template = missing.partial.as_ref().map(|partial| &partial[..]).unwrap_or(name);
+    println!("{}:{}:{}: missing {}", template, missing.line, missing.column, missing.usage);
+  }
+  for unused in report.unused.iter() {
+    println!("{}: unused {}", data_file, unused);
+  }
+
+  if report.is_valid() { 0 } else { 1 }
+}
+
+fn error(message: &str) -> i32 {
+  let _ = writeln!(io::stderr(), "{}", message);
+  2
+}
+
+fn usage_error(message: &str) -> i32 {
+  let _ = writeln!(io::stderr(), "{}\n{}", message, USAGE);
+  2
+}
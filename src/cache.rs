@@ -0,0 +1,84 @@
+use std::fmt;
+
+use serialize::json;
+use serialize::json::Json;
+use serialize::Decodable;
+
+use parse::Template;
+
+/// Version of the cache format, bumped whenever the syntax tree changes.
+pub const CACHE_VERSION: u64 = 3;
+
+#[derive(RustcDecodable)]
+struct CacheFile {
+  version: u64,
+  templates: Vec<(String, Template)>,
+}
+
+// same layout as `CacheFile`, encoding borrowed templates
+#[derive(RustcEncodable)]
+struct CacheFileRef<'a> {
+  version: u64,
+  templates: Vec<(&'a str, &'a Template)>,
+}
+
+/// Failure to read back cached templates
+#[derive(Debug)]
+pub enum CacheError {
+  Encode(json::EncoderError),
+  Syntax(json::ParserError),
+  Decode(json::DecoderError),
+  /// cache written by another version of the format, with the version found if any
+  Version(Option<u64>),
+}
+
+impl fmt::Display for CacheError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      CacheError::Encode(ref e) => write!(f, "template cache encoding failed: {}", e),
+      CacheError::Syntax(ref e) => write!(f, "invalid template cache: {}", e),
+      CacheError::Decode(ref e) => write!(f, "invalid template cache: {}", e),
+      CacheError::Version(Some(v)) => write!(f, "stale template cache, version {} instead of {}", v, CACHE_VERSION),
+      CacheError::Version(None) => write!(f, "template cache without version"),
+    }
+  }
+}
+
+/// Serializes parsed templates, to be loaded back with `decode_templates()`
+/// without parsing them again.
+///
+/// The cache is compact json, tagged with `CACHE_VERSION`.
+///
+/// ```
+/// use rumblebars::{parse, encode_templates, decode_templates};
+///
+/// let templates = vec![("hello".to_string(), parse("hello {{name}}").unwrap())];
+/// let cache = encode_templates(&templates).unwrap();
+///
+/// let loaded = decode_templates(&cache).unwrap();
+/// assert_eq!(loaded[0].1.eval_to_string(&"").unwrap(), "hello ");
+/// ```
+pub fn encode_templates(templates: &[(String, Template)]) -> Result<String, CacheError> {
+  let file = CacheFileRef {
+    version: CACHE_VERSION,
+    templates: templates.iter().map(|&(ref name, ref template)| (&name[..], template)).collect(),
+  };
+
+  json::encode(&file).map_err(CacheError::Encode)
+}
+
+/// Templates serialized by `encode_templates()`, caches of another format version
+/// are rejected.
+pub fn decode_templates(cache: &str) -> Result<Vec<(String, Template)>, CacheError> {
+  let json = try!(Json::from_str(cache).map_err(CacheError::Syntax));
+
+  match json.find("version").and_then(|v| v.as_u64()) {
+    Some(CACHE_VERSION) => (),
+    version => return Err(CacheError::Version(version)),
+  }
+
+  let mut decoder = json::Decoder::new(json);
+  let file: CacheFile = try!(Decodable::decode(&mut decoder).map_err(CacheError::Decode));
+
+  Ok(file.templates)
+}
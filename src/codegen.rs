@@ -0,0 +1,388 @@
+use std::io::Write;
+use std::fs;
+use std::fmt;
+use std::fmt::Write as FmtWrite;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+
+use serialize::json::Json;
+
+use parse::{Template, Entries, HBEntry, HBExpression, HBValHolder, RenderOptions, Position};
+use loader::{TemplateLoader, LoadError, LoadErrorKind};
+
+/// Generates rust code rendering templates without parsing them at runtime,
+/// meant to be used from build scripts.
+///
+/// The generated source has a function per template, named after the template
+/// (`shared_header` for `shared/header`), and a `render(name, data, out, eval_context)`
+/// function. Names that are rust keywords or `render` get a trailing `_`, and a
+/// numbered suffix is added until they are unique, as `item_body_1`. Raw text, values, sections on data and partials of the compiled set
+/// are rendered by generated code, as are blocks of the builtin `each`, `if`, `unless`
+/// and `with` helpers on a path. Other helpers, lambdas and partials are
+/// evaluated from a syntax tree the generated code builds once, when first needed.
+///
+/// Templates parsed with `parse_contextual()` can't be precompiled, they are
+/// reported as `LoadErrorKind::Precompile` errors.
+///
+/// # Examples
+///
+/// In `build.rs` :
+///
+/// ```no_run
+/// extern crate rumblebars;
+///
+/// use std::env;
+/// use std::path::Path;
+/// use rumblebars::{Precompiler, TemplateLoader};
+///
+/// fn main() {
+///   let out = Path::new(&env::var("OUT_DIR").unwrap()).join("templates.rs");
+///   println!("cargo:rerun-if-changed=templates");
+///
+///   if let Err(errors) = Precompiler::new(TemplateLoader::new()).compile_dir(Path::new("templates"), &out) {
+///     for error in &errors {
+///       println!("cargo:warning={}", error);
+///     }
+///     panic!("{} templates failed to compile", errors.len());
+///   }
+/// }
+/// ```
+///
+/// Then in the crate :
+///
+/// ```ignore
+/// mod templates { include!(concat!(env!("OUT_DIR"), "/templates.rs")); }
+///
+/// templates::render("shared/header", &data, &mut out, &EvalContext::new())
+/// ```
+pub struct Precompiler {
+  loader: TemplateLoader,
+}
+
+impl Precompiler {
+  /// precompiler for templates found by `loader`
+  pub fn new(loader: TemplateLoader) -> Precompiler {
+    Precompiler { loader: loader }
+  }
+
+  /// compiles all templates of the directory tree into `out_file`, errors of all
+  /// templates are reported together
+  pub fn compile_dir(&self, dir: &Path, out_file: &Path) -> Result<(), Vec<LoadError>> {
+    let templates = try!(self.loader.load(dir));
+    let source = try!(precompile(&templates));
+
+    fs::File::create(out_file).and_then(|mut f| f.write_all(source.as_bytes())).map_err(|e| {
+      vec![LoadError { path: out_file.to_path_buf(), kind: LoadErrorKind::Io(e) }]
+    })
+  }
+}
+
+/// Expression that can't be precompiled, with its position in the template.
+#[derive(Debug, Clone)]
+pub struct PrecompileError {
+  pub position: Position,
+  pub message: String,
+}
+
+impl fmt::Display for PrecompileError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}:{}: {}", self.position.line, self.position.column, self.message)
+  }
+}
+
+/// rust source rendering named `templates`, see `Precompiler`, errors of all
+/// templates are reported together, named after their template
+pub fn precompile(templates: &[(String, Template)]) -> Result<String, Vec<LoadError>> {
+  // template functions and their `_body` function share a namespace with `render`
+  let mut taken: HashSet<String> = HashSet::new();
+  taken.insert("render".to_string());
+
+  let mut idents: HashMap<&str, String> = HashMap::new();
+  for &(ref name, _) in templates {
+    let base = rust_ident(name);
+    let mut ident = base.clone();
+    let mut n = 0;
+    while taken.contains(&ident) || taken.contains(&format!("{}_body", ident)) {
+      n += 1;
+      ident = format!("{}_{}", base, n);
+    }
+    taken.insert(format!("{}_body", ident));
+    taken.insert(ident.clone());
+    idents.insert(&name[..], ident);
+  }
+
+  let mut code = String::new();
+  code.push_str("// generated by rumblebars, do not edit\n\n");
+
+  code.push_str("/// renders the precompiled template `name`\n");
+  code.push_str("pub fn render(name: &str, data: &dyn ::rumblebars::HBData, out: &mut dyn ::std::io::Write, eval_context: &::rumblebars::EvalContext) -> ::rumblebars::HBEvalResult {\n");
+  code.push_str("  match name {\n");
+  for &(ref name, _) in templates {
+    let _ = writeln!(code, "    {:?} => {}(data, out, eval_context),", name, idents[&name[..]]);
+  }
+  code.push_str("    _ => Err(::std::io::Error::new(::std::io::ErrorKind::NotFound, format!(\"no template named ‘{}’\", name))),\n");
+  code.push_str("  }\n}\n");
+
+  let mut errors = Vec::new();
+  for &(ref name, ref template) in templates {
+    let mut body = String::new();
+    if let Err(error) = write_entries(&mut body, &template.entries, &idents, 1) {
+      errors.push(LoadError { path: PathBuf::from(name), kind: LoadErrorKind::Precompile(error) });
+      continue;
+    }
+
+    let ident = &idents[&name[..]];
+    let _ = write!(code, "\n/// renders `{}`\n", name);
+    let _ = writeln!(code, "pub fn {}(data: &dyn ::rumblebars::HBData, out: &mut dyn ::std::io::Write, eval_context: &::rumblebars::EvalContext) -> ::rumblebars::HBEvalResult {{", ident);
+    let _ = writeln!(code, "  ::rumblebars::compiled::render(data, out, eval_context, {}_body)", ident);
+    code.push_str("}\n\n");
+
+    code.push_str("#[allow(unused_variables)]\n");
+    let _ = writeln!(code, "fn {}_body(r: &mut ::rumblebars::compiled::Renderer, out: &mut ::rumblebars::SafeWriting) -> ::rumblebars::HBEvalResult {{", ident);
+    code.push_str(&body);
+    code.push_str("  Ok(())\n}\n");
+  }
+
+  if errors.is_empty() { Ok(code) } else { Err(errors) }
+}
+
+/// rust expression building `template` without parsing it, as expanded by
+/// the `template!` macro of `rumblebars_macros`
+pub fn template_code(template: &Template) -> Result<String, PrecompileError> {
+  Ok(format!("::rumblebars::Template {{ entries: {} }}", try!(entry_list_code(&template.entries))))
+}
+
+const KEYWORDS: [&'static str; 52] = [
+  "_", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+  "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if",
+  "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv",
+  "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+  "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+// template name as a function name, keywords and `render` get a trailing `_`
+fn rust_ident(name: &str) -> String {
+  let ident: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+
+  let ident = match ident.chars().next() {
+    Some(c) if c.is_alphabetic() || c == '_' => ident,
+    _ => format!("_{}", ident),
+  };
+
+  if ident == "render" || KEYWORDS.contains(&&ident[..]) { format!("{}_", ident) } else { ident }
+}
+
+fn indentation(depth: usize) -> String {
+  ::std::iter::repeat("  ").take(depth).collect()
+}
+
+// statements rendering `entries`
+fn write_entries(code: &mut String, entries: &Entries, idents: &HashMap<&str, String>, depth: usize) -> Result<(), PrecompileError> {
+  let pad = indentation(depth);
+
+  for entry in entries.iter() {
+    match **entry {
+      HBEntry::Raw(ref s) => {
+        let _ = writeln!(code, "{}r.raw(out, {:?})?;", pad, s);
+      },
+      HBEntry::Comment(_) => (),
+      HBEntry::Eval(ref exp) if builtin_block(exp).is_some() => {
+        let path = path_code(builtin_block(exp).unwrap());
+        let _ = match &exp.base[0][..] {
+          "each" => writeln!(code, "{}r.each(out, {},", pad, path),
+          "with" => writeln!(code, "{}r.with(out, {},", pad, path),
+          helper => writeln!(code, "{}r.condition(out, {:?}, {}, {},", pad, helper, path, exp.render_options.inverse),
+        };
+        try!(write_block(code, exp.block.as_ref().map(|b| &**b), idents, depth + 1));
+        code.push_str(",\n");
+        try!(write_block(code, exp.else_block.as_ref().map(|b| &**b), idents, depth + 1));
+        let _ = writeln!(code, ",\n{}  &|| {}", pad, try!(lazy_entries_code(entry)));
+        let _ = writeln!(code, "{})?;", pad);
+      },
+      HBEntry::Eval(ref exp) if !is_simple(exp) => {
+        let _ = writeln!(code, "{}r.entries(out, {})?;", pad, try!(lazy_entries_code(entry)));
+      },
+      HBEntry::Eval(ref exp @ HBExpression {block: None, ..}) => {
+        let _ = writeln!(code, "{}r.value(out, {}, {}, &|| {})?;", pad, path_code(&exp.base), exp.render_options.escape, try!(lazy_entries_code(entry)));
+      },
+      HBEntry::Eval(ref exp) => {
+        let _ = writeln!(code, "{}r.section(out, {}, {},", pad, path_code(&exp.base), exp.render_options.inverse);
+        try!(write_block(code, exp.block.as_ref().map(|b| &**b), idents, depth + 1));
+        code.push_str(",\n");
+        try!(write_block(code, exp.else_block.as_ref().map(|b| &**b), idents, depth + 1));
+        let _ = writeln!(code, ",\n{}  &|| {}", pad, try!(lazy_entries_code(entry)));
+        let _ = writeln!(code, "{})?;", pad);
+      },
+      HBEntry::Partial(ref exp) => {
+        let compiled = idents.get(&exp.partial_name()[..]);
+        let context = match exp.params.first() {
+          None => Some("None".to_string()),
+          Some(&HBValHolder::Path(ref p)) if exp.params.len() == 1 => Some(format!("Some({})", path_code(p))),
+          _ => None,
+        };
+
+        match (compiled, context) {
+          (Some(ident), Some(context)) if exp.options.is_empty() => {
+            let indent = match exp.render_options.indent {
+              Some(ref indent) => format!("Some({:?})", indent),
+              None => "None".to_string(),
+            };
+            let _ = writeln!(code, "{}r.partial(out, {}, {}, {}_body)?;", pad, indent, context, ident);
+          },
+          _ => {
+            let _ = writeln!(code, "{}r.entries(out, {})?;", pad, try!(lazy_entries_code(entry)));
+          },
+        }
+      },
+    }
+  }
+
+  Ok(())
+}
+
+// block closure of a section or a builtin helper
+fn write_block(code: &mut String, block: Option<&Entries>, idents: &HashMap<&str, String>, depth: usize) -> Result<(), PrecompileError> {
+  let pad = indentation(depth);
+  let _ = writeln!(code, "{}&|r, out| {{", pad);
+  if let Some(entries) = block {
+    try!(write_entries(code, entries, idents, depth + 1));
+  }
+  let _ = write!(code, "{}  Ok(())\n{}}}", pad, pad);
+  Ok(())
+}
+
+// values and sections without parameters are compiled, others go through evaluation
+fn is_simple(exp: &HBExpression) -> bool {
+  exp.params.is_empty() && exp.options.is_empty() && exp.render_options.context.is_none() && !exp.base.is_empty()
+}
+
+const BUILTIN_BLOCKS: [&'static str; 4] = ["each", "if", "unless", "with"];
+
+// path parameter of a builtin helper block, compiled unless the helper is replaced
+fn builtin_block(exp: &HBExpression) -> Option<&Vec<String>> {
+  if exp.base.len() != 1 || !BUILTIN_BLOCKS.contains(&&exp.base[0][..]) || exp.block.is_none() {
+    return None;
+  }
+  if !exp.options.is_empty() || exp.render_options.context.is_some() {
+    return None;
+  }
+
+  match exp.params.first() {
+    Some(&HBValHolder::Path(ref path)) if exp.params.len() == 1 => Some(path),
+    _ => None,
+  }
+}
+
+fn path_code(path: &Vec<String>) -> String {
+  format!("&[{}]", path.iter().map(|k| format!("{:?}", k)).collect::<Vec<_>>().join(", "))
+}
+
+fn string_code(s: &str) -> String {
+  format!("{:?}.to_string()", s)
+}
+
+fn option_code<T, F>(v: &Option<T>, f: F) -> String where F: Fn(&T) -> String {
+  match *v {
+    Some(ref v) => format!("Some({})", f(v)),
+    None => "None".to_string(),
+  }
+}
+
+fn entries_option_code(v: &Option<Box<Entries>>) -> Result<String, PrecompileError> {
+  match *v {
+    Some(ref entries) => Ok(format!("Some({})", try!(entries_code(entries)))),
+    None => Ok("None".to_string()),
+  }
+}
+
+// expression building the syntax tree of `entry`
+fn entry_code(entry: &HBEntry) -> Result<String, PrecompileError> {
+  Ok(match *entry {
+    HBEntry::Raw(ref s) => format!("::rumblebars::HBEntry::Raw({})", string_code(s)),
+    HBEntry::Eval(ref exp) => format!("::rumblebars::HBEntry::Eval({})", try!(expression_code(exp))),
+    HBEntry::Partial(ref exp) => format!("::rumblebars::HBEntry::Partial({})", try!(expression_code(exp))),
+    HBEntry::Comment(ref comment) => format!(
+      "::rumblebars::HBEntry::Comment(::rumblebars::HBComment {{ source: {}, render_options: {}, position: {} }})",
+      string_code(&comment.source),
+      render_options_code(&comment.render_options),
+      position_code(&comment.position)
+    ),
+  })
+}
+
+// expression building the syntax tree of `entry` once, on its first evaluation,
+// as a `&'static Entries`
+fn lazy_entries_code(entry: &HBEntry) -> Result<String, PrecompileError> {
+  Ok(format!(
+    "{{ static ENTRIES: ::std::sync::OnceLock<::rumblebars::Entries> = ::std::sync::OnceLock::new(); ENTRIES.get_or_init(|| vec![Box::new({})]) }}",
+    try!(entry_code(entry))
+  ))
+}
+
+fn entry_list_code(entries: &Entries) -> Result<String, PrecompileError> {
+  let mut codes = Vec::new();
+  for entry in entries.iter() {
+    codes.push(format!("Box::new({})", try!(entry_code(entry))));
+  }
+  Ok(format!("vec![{}]", codes.join(", ")))
+}
+
+fn entries_code(entries: &Entries) -> Result<String, PrecompileError> {
+  Ok(format!("Box::new({})", try!(entry_list_code(entries))))
+}
+
+fn expression_code(exp: &HBExpression) -> Result<String, PrecompileError> {
+  if exp.render_options.context.is_some() {
+    return Err(PrecompileError {
+      position: exp.position,
+      message: format!("contextual autoescaping of ‘{}’ can't be precompiled", exp.path()),
+    });
+  }
+
+  Ok(format!(
+    "::rumblebars::HBExpression {{ base: vec![{}], params: vec![{}], options: vec![{}], render_options: {}, block: {}, else_block: {}, block_source: {}, position: {} }}",
+    exp.base.iter().map(|k| string_code(k)).collect::<Vec<_>>().join(", "),
+    exp.params.iter().map(value_code).collect::<Vec<_>>().join(", "),
+    exp.options.iter().map(|&(ref k, ref v)| format!("({}, {})", string_code(k), value_code(v))).collect::<Vec<_>>().join(", "),
+    render_options_code(&exp.render_options),
+    try!(entries_option_code(&exp.block)),
+    try!(entries_option_code(&exp.else_block)),
+    option_code(&exp.block_source, |s| string_code(s)),
+    position_code(&exp.position)
+  ))
+}
+
+fn render_options_code(render_options: &RenderOptions) -> String {
+  format!(
+    "::rumblebars::RenderOptions {{ escape: {}, inverse: {}, indent: {}, no_leading_whitespace: {}, no_trailing_whitespace: {}, context: None }}",
+    render_options.escape,
+    render_options.inverse,
+    option_code(&render_options.indent, |s| string_code(s)),
+    render_options.no_leading_whitespace,
+    render_options.no_trailing_whitespace
+  )
+}
+
+fn position_code(position: &Position) -> String {
+  format!("::rumblebars::Position {{ line: {}, column: {} }}", position.line, position.column)
+}
+
+fn value_code(value: &HBValHolder) -> String {
+  match *value {
+    HBValHolder::String(ref s) => format!("::rumblebars::HBValHolder::String({})", string_code(s)),
+    HBValHolder::Path(ref p) => format!("::rumblebars::HBValHolder::Path(vec![{}])", p.iter().map(|k| string_code(k)).collect::<Vec<_>>().join(", ")),
+    HBValHolder::Literal(ref json, ref s) => format!("::rumblebars::HBValHolder::Literal({}, {})", json_code(json), string_code(s)),
+  }
+}
+
+// literals are scalars
+fn json_code(json: &Json) -> String {
+  match *json {
+    Json::I64(i) => format!("::rumblebars::compiled::Json::I64({})", i),
+    Json::U64(u) => format!("::rumblebars::compiled::Json::U64({})", u),
+    Json::F64(f) => format!("::rumblebars::compiled::Json::F64({:?})", f),
+    Json::Boolean(b) => format!("::rumblebars::compiled::Json::Boolean({})", b),
+    Json::String(ref s) => format!("::rumblebars::compiled::Json::String({})", string_code(s)),
+    _ => "::rumblebars::compiled::Json::Null".to_string(),
+  }
+}
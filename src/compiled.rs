@@ -0,0 +1,236 @@
+//! Runtime support of precompiled templates, used by the code generated with
+//! [`Precompiler`](../struct.Precompiler.html), not meant to be called directly.
+//!
+//! Raw text, values, sections on data and blocks of the builtin `each`, `if`,
+//! `unless` and `with` helpers are rendered by generated code. Other helpers,
+//! lambdas and partials with parameters are evaluated from their syntax tree,
+//! built once by the generated code when first needed.
+
+use std::io;
+use std::io::Write;
+use std::collections::HashMap;
+
+use serialize::json::ToJson;
+
+use eval::{HBData, HBNodeType, HBEvalResult, EvalContext, SafeWriting, value_for_key_path_in_context, eval_with_globals, with_indent};
+use escape::EscapeWriter;
+use parse::Entries;
+
+pub use serialize::json::Json;
+
+/// signature of generated rendering functions
+pub type CompiledFunction = fn(&mut Renderer, &mut SafeWriting) -> HBEvalResult;
+
+/// block of a builtin helper, rendered with the data of each item by `each`
+pub type Block<'b> = &'b Fn(&mut Renderer, &mut SafeWriting) -> HBEvalResult;
+
+/// syntax tree of an entry, built once by the generated code
+pub type Fallback<'b> = &'b Fn() -> &'static Entries;
+
+// missing values, falsy as json null
+static NULL: () = ();
+
+/// Evaluation state of a precompiled template: current data, parent contexts,
+/// globals and evaluation context.
+pub struct Renderer<'a> {
+  data: &'a HBData,
+  stack: Vec<&'a HBData>,
+  globals: HashMap<&'static str, &'a HBData>,
+  eval_context: &'a EvalContext,
+}
+
+/// renders a compiled template with the same setup as `eval()`
+pub fn render(data: &HBData, out: &mut io::Write, eval_context: &EvalContext, body: CompiledFunction) -> HBEvalResult {
+  let log = "info".to_string();
+  let mut globals = HashMap::new();
+  globals.insert("@root", data);
+  globals.insert("@level", &log as &HBData);
+
+  let mut renderer = Renderer {
+    data: data,
+    stack: vec![data],
+    globals: globals,
+    eval_context: eval_context,
+  };
+
+  let mut escape_writer = EscapeWriter::new(out, eval_context.escaper());
+  try!(body(&mut renderer, &mut SafeWriting::Safe(&mut escape_writer)));
+
+  escape_writer.finish()
+}
+
+impl <'a> Renderer<'a> {
+  pub fn raw(&mut self, out: &mut SafeWriting, s: &str) -> HBEvalResult {
+    out.into_unsafe().write_all(s.as_bytes())
+  }
+
+  /// `{{path}}` and `{{{path}}}`, helpers and lambdas are evaluated from `fallback`
+  pub fn value(&mut self, out: &mut SafeWriting, path: &[&str], escape: bool, fallback: Fallback) -> HBEvalResult {
+    if self.is_helper(path) {
+      return self.entries(out, fallback());
+    }
+
+    match self.lookup(path) {
+      Some(v) if v.as_lambda().is_some() => self.entries(out, fallback()),
+      Some(v) => if escape { v.write_value(out) } else { v.write_value(&mut out.into_unsafe()) },
+      None => Ok(()),
+    }
+  }
+
+  /// `{{#path}}…{{else}}…{{/path}}` and `{{^path}}…{{/path}}` on data
+  pub fn section(
+    &mut self,
+    out: &mut SafeWriting,
+    path: &[&str],
+    inverse: bool,
+    block: &Fn(&mut Renderer<'a>, &mut SafeWriting) -> HBEvalResult,
+    else_block: &Fn(&mut Renderer<'a>, &mut SafeWriting) -> HBEvalResult,
+    fallback: Fallback
+  ) -> HBEvalResult {
+    if self.is_helper(path) {
+      return self.entries(out, fallback());
+    }
+
+    let c = self.lookup(path).unwrap_or(&NULL);
+    if c.as_lambda().is_some() {
+      return self.entries(out, fallback());
+    }
+
+    match c.typed_node() {
+      HBNodeType::Branch(_) | HBNodeType::Leaf(_) | HBNodeType::Null => {
+        if c.as_bool() != inverse {
+          self.with_data(c, out, block)
+        } else {
+          else_block(self, out)
+        }
+      },
+      HBNodeType::Array(_) => {
+        let mut rendered = false;
+        if !inverse {
+          for item in c.values() {
+            rendered = true;
+            try!(self.with_data(item, out, block));
+          }
+        } else if c.values().next().is_none() {
+          rendered = true;
+          try!(self.with_data(&NULL, out, block));
+        }
+
+        if rendered { Ok(()) } else { else_block(self, out) }
+      },
+    }
+  }
+
+  /// `{{> name context}}` of a compiled partial, indented as standalone partials
+  pub fn partial(&mut self, out: &mut SafeWriting, indent: Option<&str>, context: Option<&[&str]>, body: CompiledFunction) -> HBEvalResult {
+    let indent = indent.and_then(|s| if s.is_empty() { None } else { Some(s.to_string()) });
+    let data = self.data;
+    let partial_data = context.and_then(|path| self.lookup(path)).unwrap_or(data);
+
+    self.data = partial_data;
+    let result = with_indent(indent, out, |out| body(self, out));
+    self.data = data;
+
+    result
+  }
+
+  /// `{{#if path}}…{{else}}…{{/if}}` and `{{#unless path}}…{{/unless}}`, as the
+  /// builtin helpers, `helper` is evaluated from `fallback` once replaced
+  pub fn condition(&mut self, out: &mut SafeWriting, helper: &str, path: &[&str], inverse: bool, block: Block, else_block: Block, fallback: Fallback) -> HBEvalResult {
+    if !self.eval_context.has_builtin_helper(helper) {
+      return self.entries(out, fallback());
+    }
+
+    let condition = self.lookup(path).map_or(false, |v| v.as_bool()) != inverse;
+    if condition != (helper == "unless") { block(self, out) } else { else_block(self, out) }
+  }
+
+  /// `{{#with path}}…{{else}}…{{/with}}`, as the builtin helper
+  pub fn with(&mut self, out: &mut SafeWriting, path: &[&str], block: Block, else_block: Block, fallback: Fallback) -> HBEvalResult {
+    if !self.eval_context.has_builtin_helper("with") {
+      return self.entries(out, fallback());
+    }
+
+    match self.param(path) {
+      Some(c) if c.as_bool() => {
+        // helpers render their block without stacking the current data
+        let data = self.data;
+        self.data = c;
+        let result = block(self, out);
+        self.data = data;
+        result
+      },
+      _ => else_block(self, out),
+    }
+  }
+
+  /// `{{#each path}}…{{else}}…{{/each}}` on arrays, as the builtin helper, keys of
+  /// objects are iterated by the helper from `fallback`
+  pub fn each(&mut self, out: &mut SafeWriting, path: &[&str], block: Block, else_block: Block, fallback: Fallback) -> HBEvalResult {
+    if !self.eval_context.has_builtin_helper("each") {
+      return self.entries(out, fallback());
+    }
+
+    let c = self.param(path).unwrap_or(&NULL);
+    match c.typed_node() {
+      HBNodeType::Array(_) => {
+        let (len, _) = c.values().size_hint();
+        if len == 0 {
+          return else_block(self, out);
+        }
+
+        for (index, item) in c.values().enumerate() {
+          let d_index = index.to_json();
+          let first = (index == 0).to_json();
+          let last = (index == len - 1).to_json();
+
+          let mut globals: HashMap<&'static str, &HBData> = self.globals.clone();
+          globals.insert("@index", &d_index as &HBData);
+          globals.insert("@first", &first as &HBData);
+          globals.insert("@last", &last as &HBData);
+
+          let mut renderer = Renderer {
+            data: item,
+            stack: self.stack.clone(),
+            globals: globals,
+            eval_context: self.eval_context,
+          };
+          try!(block(&mut renderer, out));
+        }
+        Ok(())
+      },
+      HBNodeType::Branch(_) => self.entries(out, fallback()),
+      HBNodeType::Leaf(_) | HBNodeType::Null => block(self, out),
+    }
+  }
+
+  /// evaluates syntax tree entries in the current context
+  pub fn entries(&mut self, out: &mut SafeWriting, entries: &Entries) -> HBEvalResult {
+    eval_with_globals(entries, self.data, out, self.eval_context, &self.globals, &self.stack, None)
+  }
+
+  fn is_helper(&self, path: &[&str]) -> bool {
+    path.len() == 1 && self.eval_context.has_helper_with_name(path[0])
+  }
+
+  fn lookup(&self, path: &[&str]) -> Option<&'a HBData> {
+    value_for_key_path_in_context(self.data, path, &self.stack, &self.globals, self.eval_context.compat)
+  }
+
+  // helper parameters are looked up without recursion
+  fn param(&self, path: &[&str]) -> Option<&'a HBData> {
+    value_for_key_path_in_context(self.data, path, &self.stack, &self.globals, false)
+  }
+
+  fn with_data(&mut self, data: &'a HBData, out: &mut SafeWriting, block: &Fn(&mut Renderer<'a>, &mut SafeWriting) -> HBEvalResult) -> HBEvalResult {
+    let parent = self.data;
+    self.stack.push(parent);
+    self.data = data;
+
+    let result = block(self, out);
+
+    self.data = parent;
+    self.stack.pop();
+    result
+  }
+}
@@ -0,0 +1,260 @@
+use std::io;
+use std::borrow::Cow;
+
+use eval::SafeWriter;
+
+/// Escaping strategy used to write values of `{{expression}}`, the
+/// `{{{expression}}}` form always writes raw values.
+///
+/// Implementors only have to provide a replacement for characters that
+/// need escaping, unchanged text is written in runs.
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+/// use rumblebars::{Template, EvalContext, Escaper};
+///
+/// struct ShellEscaper;
+///
+/// impl Escaper for ShellEscaper {
+///   // for values used inside single quotes
+///   fn escape_char(&self, c: char) -> Option<Cow<'static, str>> {
+///     if c == '\'' { Some(Cow::Borrowed("'\\''")) } else { None }
+///   }
+/// }
+///
+/// let mut context = EvalContext::new();
+/// context.set_escaper(Box::new(ShellEscaper));
+///
+/// let mut buf = Vec::new();
+/// Template::new("echo '{{.}}'").unwrap().eval(&"it's", &mut buf, &context).unwrap();
+///
+/// assert_eq!(String::from_utf8(buf).unwrap(), "echo 'it'\\''s'");
+/// ```
+pub trait Escaper {
+  /// replacement for a character, `None` when it is written as is
+  fn escape_char(&self, c: char) -> Option<Cow<'static, str>>;
+
+  /// writes escaped text
+  fn escape(&self, s: &str, out: &mut io::Write) -> io::Result<()> {
+    let mut run_start = 0;
+    for (i, c) in s.char_indices() {
+      if let Some(replacement) = self.escape_char(c) {
+        try!(out.write_all(s[run_start..i].as_bytes()));
+        try!(out.write_all(replacement.as_bytes()));
+        run_start = i + c.len_utf8();
+      }
+    }
+
+    out.write_all(s[run_start..].as_bytes())
+  }
+}
+
+/// html escaping, the default
+pub struct HTMLEscaper;
+
+impl Escaper for HTMLEscaper {
+  fn escape_char(&self, c: char) -> Option<Cow<'static, str>> {
+    match c {
+      '<'  => Some(Cow::Borrowed("&lt;")),
+      '>'  => Some(Cow::Borrowed("&gt;")),
+      '&'  => Some(Cow::Borrowed("&amp;")),
+      '"'  => Some(Cow::Borrowed("&quot;")),
+      '\'' => Some(Cow::Borrowed("&#x27;")),
+      '`'  => Some(Cow::Borrowed("&#x60;")),
+      _ => None,
+    }
+  }
+}
+
+/// content of a json string, template provides the surrounding quotes
+pub struct JSONStringEscaper;
+
+impl Escaper for JSONStringEscaper {
+  fn escape_char(&self, c: char) -> Option<Cow<'static, str>> {
+    match c {
+      '"'  => Some(Cow::Borrowed("\\\"")),
+      '\\' => Some(Cow::Borrowed("\\\\")),
+      '\n' => Some(Cow::Borrowed("\\n")),
+      '\r' => Some(Cow::Borrowed("\\r")),
+      '\t' => Some(Cow::Borrowed("\\t")),
+      '\u{8}' => Some(Cow::Borrowed("\\b")),
+      '\u{c}' => Some(Cow::Borrowed("\\f")),
+      c if c < ' ' => Some(Cow::Owned(format!("\\u{:04x}", c as u32))),
+      _ => None,
+    }
+  }
+}
+
+/// content of a javascript string, single or double quoted, that is safe
+/// to embed in html (no `<`, `>` nor `&`)
+pub struct JavascriptEscaper;
+
+impl Escaper for JavascriptEscaper {
+  fn escape_char(&self, c: char) -> Option<Cow<'static, str>> {
+    match c {
+      '\'' => Some(Cow::Borrowed("\\'")),
+      '<' | '>' | '&' | '=' | '`' | '\u{2028}' | '\u{2029}' => Some(Cow::Owned(format!("\\u{:04x}", c as u32))),
+      c => JSONStringEscaper.escape_char(c),
+    }
+  }
+}
+
+/// percent encoding of url components, only unreserved characters are kept
+pub struct URLEscaper;
+
+impl Escaper for URLEscaper {
+  fn escape_char(&self, c: char) -> Option<Cow<'static, str>> {
+    match c {
+      'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '~' => None,
+      c => {
+        let mut buf = [0; 4];
+        Some(Cow::Owned(c.encode_utf8(&mut buf).bytes().map(|b| format!("%{:02X}", b)).collect()))
+      }
+    }
+  }
+}
+
+/// content of a quoted csv field (`"{{field}}"`), quotes are doubled
+pub struct CSVEscaper;
+
+impl Escaper for CSVEscaper {
+  fn escape_char(&self, c: char) -> Option<Cow<'static, str>> {
+    if c == '"' { Some(Cow::Borrowed("\"\"")) } else { None }
+  }
+}
+
+/// LaTeX text, special characters are escaped or replaced by their text command
+pub struct LatexEscaper;
+
+impl Escaper for LatexEscaper {
+  fn escape_char(&self, c: char) -> Option<Cow<'static, str>> {
+    match c {
+      '\\' => Some(Cow::Borrowed("\\textbackslash{}")),
+      '~'  => Some(Cow::Borrowed("\\textasciitilde{}")),
+      '^'  => Some(Cow::Borrowed("\\textasciicircum{}")),
+      '{' | '}' | '$' | '&' | '#' | '_' | '%' => Some(Cow::Owned(format!("\\{}", c))),
+      _ => None,
+    }
+  }
+}
+
+/// markdown text, punctuation with a markdown meaning is backslash escaped
+pub struct MarkdownEscaper;
+
+impl Escaper for MarkdownEscaper {
+  fn escape_char(&self, c: char) -> Option<Cow<'static, str>> {
+    match c {
+      '\\' | '`' | '*' | '_' | '{' | '}' | '[' | ']' | '(' | ')' | '#' | '+' | '-' | '.' | '!' | '|' | '<' | '>' => {
+        Some(Cow::Owned(format!("\\{}", c)))
+      },
+      _ => None,
+    }
+  }
+}
+
+/// writes everything as is
+pub struct NoEscaper;
+
+impl Escaper for NoEscaper {
+  fn escape_char(&self, _: char) -> Option<Cow<'static, str>> { None }
+
+  fn escape(&self, s: &str, out: &mut io::Write) -> io::Result<()> {
+    out.write_all(s.as_bytes())
+  }
+}
+
+/// `SafeWriter` that escapes written text with an `Escaper`.
+///
+/// Text can be written in arbitrary chunks, a character split between two
+/// writes is escaped once complete, `finish` reports a character left
+/// incomplete.
+pub struct EscapeWriter<'a> {
+  raw: Unescaped<'a>,
+  escaper: &'a (Escaper + 'a),
+}
+
+// wrapped writer, bytes of an incomplete character are written before
+// unescaped text, so that output keeps its order
+struct Unescaped<'a> {
+  w: &'a mut (io::Write + 'a),
+  pending: Vec<u8>,
+}
+
+impl <'a> EscapeWriter<'a> {
+  pub fn new(writer: &'a mut (io::Write + 'a), escaper: &'a (Escaper + 'a)) -> EscapeWriter<'a> {
+    EscapeWriter {
+      raw: Unescaped { w: writer, pending: Vec::new() },
+      escaper: escaper,
+    }
+  }
+
+  /// checks that all written text has been passed to the wrapped writer
+  pub fn finish(self) -> io::Result<()> {
+    if self.raw.pending.is_empty() {
+      Ok(())
+    } else {
+      Err(io::Error::new(io::ErrorKind::InvalidData, "incomplete utf8 character at end of output"))
+    }
+  }
+}
+
+/// longest valid utf8 prefix of `bytes`, only an incomplete character at
+/// the end is left out, invalid utf8 is an error
+pub fn utf8_prefix(bytes: &[u8]) -> io::Result<&str> {
+  let valid_len = match ::std::str::from_utf8(bytes) {
+    Ok(_) => bytes.len(),
+    Err(ref e) if e.error_len().is_none() => e.valid_up_to(), // incomplete character at the end
+    Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+  };
+
+  Ok(unsafe { ::std::str::from_utf8_unchecked(&bytes[..valid_len]) })
+}
+
+impl <'a> io::Write for EscapeWriter<'a> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let raw = &mut self.raw;
+
+    if raw.pending.is_empty() {
+      let valid = try!(utf8_prefix(buf));
+      try!(self.escaper.escape(valid, raw.w));
+      raw.pending.extend_from_slice(&buf[valid.len()..]);
+    } else {
+      let mut bytes = ::std::mem::replace(&mut raw.pending, Vec::new());
+      bytes.extend_from_slice(buf);
+      let valid_len = {
+        let valid = try!(utf8_prefix(&bytes));
+        try!(self.escaper.escape(valid, raw.w));
+        valid.len()
+      };
+      raw.pending.extend_from_slice(&bytes[valid_len..]);
+    }
+
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.raw.w.flush()
+  }
+}
+
+impl <'a> io::Write for Unescaped<'a> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    if !self.pending.is_empty() {
+      let pending = ::std::mem::replace(&mut self.pending, Vec::new());
+      try!(self.w.write_all(&pending));
+    }
+    self.w.write(buf)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.w.flush()
+  }
+}
+
+impl <'a> SafeWriter for EscapeWriter<'a> {
+  fn writer(&mut self) -> &mut io::Write {
+    &mut self.raw
+  }
+}
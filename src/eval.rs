@@ -1,21 +1,29 @@
 use std::io;
 use std::io::Write;
+use std::fmt;
 use serialize::json::Json;
 use std::collections::HashMap;
+use std::rc::Rc;
 use std::vec::Vec;
 use std::default::Default;
 use regex::Regex;
 
+use parse::parse;
 use parse::Template;
 use parse::Entries;
 use parse::HBEntry;
 use parse::HBExpression;
 use parse::HBValHolder;
+use hbdata_layers::Layers;
+use hbdata_lambda::Lambda;
+use escape::{Escaper, HTMLEscaper, EscapeWriter, utf8_prefix};
+use autoescape::{HTMLContext, escape_in_context};
+use analysis::{Analysis, analyze};
 
 
-fn value_for_key_path_in_context<'a>(
+pub fn value_for_key_path_in_context<'a, K: AsRef<str>>(
   data: &'a HBData,
-  key_path: &Vec<String>,
+  key_path: &[K],
   context_stack: &Vec<&'a HBData>,
   global_data: &HashMap<&str, &'a HBData>,
   compat: bool,
@@ -25,7 +33,7 @@ fn value_for_key_path_in_context<'a>(
   let mut stack_index = 0;
   let mut first_key = true;
 
-  for key in key_path.iter().map(|k| &k[..]) {
+  for key in key_path.iter().map(|k| k.as_ref()) {
     match key {
       "."  => {continue},
       ".." => {
@@ -96,55 +104,90 @@ struct IndentWriter<'a> {
 }
 
 impl <'a> IndentWriter<'a> {
-  fn with_indent(s: Option<String>, out: &mut SafeWriting, funkt: &Fn(&mut SafeWriting) -> io::Result<()>) -> io::Result<()> {
-    let mut indenter = IndentWriter {w: out, indent: s};
-    let mut safe = SafeWriting::Unsafe(&mut indenter);
-    funkt(&mut safe)
+  // indents what `funkt` writes, keeping `out` escaping and opt-out with `into_unsafe()`
+  fn with_indent<F>(s: Option<String>, out: &mut SafeWriting, mut funkt: F) -> io::Result<()> where F: FnMut(&mut SafeWriting) -> io::Result<()> {
+    match (s, out) {
+      (None, out) => funkt(out),
+      (indent, &mut SafeWriting::Safe(ref mut w)) => {
+        let mut indenter = SafeIndentWriter {raw: RawIndentWriter {w: &mut **w, indent: indent}};
+        funkt(&mut SafeWriting::Safe(&mut indenter))
+      },
+      (indent, &mut SafeWriting::Unsafe(ref mut w)) => {
+        let mut indenter = IndentWriter {w: &mut **w, indent: indent};
+        funkt(&mut SafeWriting::Unsafe(&mut indenter))
+      },
+    }
   }
 }
 
+/// indents what `funkt` writes after each new line, see `IndentWriter::with_indent`
+pub fn with_indent<F>(indent: Option<String>, out: &mut SafeWriting, funkt: F) -> io::Result<()> where F: FnMut(&mut SafeWriting) -> io::Result<()> {
+  IndentWriter::with_indent(indent, out, funkt)
+}
+
+// writes `buf`, indenting after each new line. Only `\n` bytes are looked for,
+// which never occur inside multibyte utf8 sequences, so chunks may split characters
+fn write_indented<W: io::Write + ?Sized>(writer: &mut W, indent: &Option<String>, buf: &[u8]) -> io::Result<usize> {
+  match *indent {
+    None => writer.write(buf),
+    Some(ref indent_str) => {
+      let mut run_start = 0;
+      for (i, _) in buf.iter().enumerate().filter(|&(_, b)| *b == b'\n') {
+        try!(writer.write_all(&buf[run_start..i + 1]));
+        try!(writer.write_all(indent_str.as_bytes()));
+        run_start = i + 1;
+      }
+
+      try!(writer.write_all(&buf[run_start..]));
+      Ok(buf.len())
+    },
+  }
+}
 
 impl <'a> io::Write for IndentWriter<'a> {
   fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-    match self.indent {
-      None => self.w.write(buf),
-      Some(ref indent_str) => {
-        let mut r = Ok(0);
-        let ref mut writer = self.w;
-        let as_utf8 = unsafe { ::std::str::from_utf8_unchecked(buf) };
-        let mut chars = as_utf8.char_indices();
-        let mut i = chars.next().unwrap_or((0, ' ')).0; // init with first char
-        while i < as_utf8.len() {
-          match chars.next().or(Some((as_utf8.len(), ' '))) {
-            Some((next, _)) => {
-              r = match &as_utf8[i..next] {
-                "\n"  => {
-                  writer.write("\n".as_bytes()).and_then(|_| {
-                    writer.write(&indent_str.as_bytes())
-                  })
-                },
-                chr => {
-                  writer.write(chr.as_bytes())
-                }
-              };
+    write_indented(&mut *self.w, &self.indent, buf)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.w.flush()
+  }
+}
 
-              i = next;
+// indentation over an escaping writer, its unsafe writer is indented too
+struct SafeIndentWriter<'a> {
+  raw: RawIndentWriter<'a>,
+}
 
-              if r.is_err() {
-                break;
-              }
-            },
-            None => break,
-          }
-        }
+// indents the unsafe writer of `w`, fetched again on each write
+struct RawIndentWriter<'a> {
+  w: &'a mut (SafeWriter+'a),
+  indent: Option<String>,
+}
 
-        r.and(Ok(buf.len()))
-      },
-    }
+impl <'a> io::Write for SafeIndentWriter<'a> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    write_indented(&mut *self.raw.w, &self.raw.indent, buf)
   }
 
   fn flush(&mut self) -> io::Result<()> {
-    self.w.flush()
+    self.raw.w.flush()
+  }
+}
+
+impl <'a> io::Write for RawIndentWriter<'a> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    write_indented(self.w.writer(), &self.indent, buf)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.w.writer().flush()
+  }
+}
+
+impl <'a> SafeWriter for SafeIndentWriter<'a> {
+  fn writer(&mut self) -> &mut io::Write {
+    &mut self.raw
   }
 }
 
@@ -243,39 +286,30 @@ impl <'a> HTMLSafeWriter<'a> {
 }
 
 impl <'a> io::Write for HTMLSafeWriter<'a> {
+  // escaped characters are all ascii, so bytes are scanned without utf8 decoding
+  // and unchanged runs are written at once
   fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-    let mut r = Ok(0);
     let writer = self.writer();
-
-    let as_utf8 = unsafe { ::std::str::from_utf8_unchecked(buf) };
-    let mut chars = as_utf8.char_indices();
-    let mut i = chars.next().unwrap_or((0, ' ')).0; // init with first char
-    while i < as_utf8.len() {
-      match chars.next().or(Some((as_utf8.len(), ' '))) {
-        Some((next, _)) => {
-
-          r = match &as_utf8[i..next] {
-            "<"  => writer.write("&lt;".as_bytes()),
-            ">"  => writer.write("&gt;".as_bytes()),
-            "&"  => writer.write("&amp;".as_bytes()),
-            "\"" => writer.write("&quot;".as_bytes()),
-            "\'" => writer.write("&#x27;".as_bytes()),
-            "`"  => writer.write("&#x60;".as_bytes()),
-            "\\" => writer.write("\\".as_bytes()),
-            chr  => writer.write(chr.as_bytes()),
-          };
-
-          i = next;
-
-          if r.is_err() {
-            break;
-          }
-        },
-        None => break,
-      }
+    let mut run_start = 0;
+
+    for (i, b) in buf.iter().enumerate() {
+      let escaped: &[u8] = match *b {
+        b'<'  => b"&lt;",
+        b'>'  => b"&gt;",
+        b'&'  => b"&amp;",
+        b'"'  => b"&quot;",
+        b'\'' => b"&#x27;",
+        b'`'  => b"&#x60;",
+        _ => continue,
+      };
+
+      try!(writer.write_all(&buf[run_start..i]));
+      try!(writer.write_all(escaped));
+      run_start = i + 1;
     }
 
-    r.and(Ok(buf.len()))
+    try!(writer.write_all(&buf[run_start..]));
+    Ok(buf.len())
   }
 
   fn flush(&mut self) -> io::Result<()> {
@@ -289,11 +323,80 @@ impl <'a> SafeWriter for HTMLSafeWriter<'a> {
   }
 }
 
+/// Writer adapter to a `fmt::Write` (a `String`, a `Formatter`), text is
+/// passed as `&str` without intermediate buffer.
+///
+/// A character split between two writes is passed once complete, `finish`
+/// reports a character left incomplete.
+pub struct FmtWriter<'a> {
+  w: &'a mut (fmt::Write + 'a),
+  pending: Vec<u8>,
+}
+
+impl <'a> FmtWriter<'a> {
+  pub fn new(writer: &'a mut (fmt::Write + 'a)) -> FmtWriter<'a> {
+    FmtWriter {
+      w: writer,
+      pending: Vec::new(),
+    }
+  }
+
+  /// checks that all written text has been passed to the wrapped writer
+  pub fn finish(self) -> io::Result<()> {
+    if self.pending.is_empty() {
+      Ok(())
+    } else {
+      Err(io::Error::new(io::ErrorKind::InvalidData, "incomplete utf8 character at end of output"))
+    }
+  }
+
+  fn write_str(w: &mut fmt::Write, s: &str) -> io::Result<()> {
+    w.write_str(s).map_err(|_| io::Error::new(io::ErrorKind::Other, "formatter error"))
+  }
+}
+
+impl <'a> io::Write for FmtWriter<'a> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    if self.pending.is_empty() {
+      let valid = try!(utf8_prefix(buf));
+      try!(FmtWriter::write_str(self.w, valid));
+      self.pending.extend_from_slice(&buf[valid.len()..]);
+    } else {
+      let mut bytes = ::std::mem::replace(&mut self.pending, Vec::new());
+      bytes.extend_from_slice(buf);
+      let valid_len = {
+        let valid = try!(utf8_prefix(&bytes));
+        try!(FmtWriter::write_str(self.w, valid));
+        valid.len()
+      };
+      self.pending.extend_from_slice(&bytes[valid_len..]);
+    }
+
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
 pub type HBEvalResult = io::Result<()>;
 pub type HBKeysIter<'a> = Box<Iterator<Item = &'a str> + 'a>;
 pub type HBValuesIter<'a> = Box<Iterator<Item = &'a (HBData + 'a)> + 'a>;
 pub type HBIter<'a> = Box<Iterator<Item = (&'a str, &'a (HBData + 'a))> + 'a>;
 
+/// writes values as a comma separated list, the way arrays are rendered
+pub fn write_values_list(values: HBValuesIter, out: &mut SafeWriting) -> HBEvalResult {
+  for (index, e) in values.enumerate() {
+    if index > 0 {
+      try!(write!(out, ","));
+    }
+    try!(e.write_value(out));
+  }
+
+  Ok(())
+}
+
 pub trait HBData  {
   fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult;
 
@@ -305,6 +408,9 @@ pub trait HBData  {
   fn values<'a>(&'a self) -> HBValuesIter<'a>;
 
   fn iter<'a>(&'a self)   -> HBIter<'a>;
+
+  /// callable node, rendered as a mustache lambda (see [`Lambda`](struct.Lambda.html))
+  fn as_lambda(&self) -> Option<&Lambda> { None }
 }
 
 
@@ -326,22 +432,7 @@ impl HBData for Json {
       &Json::F64(ref f)     => write!(out, "{}", f),
       &Json::String(ref s)  => write!(out, "{}", s),
       &Json::Boolean(ref b) => write!(out, "{}", b),
-      &Json::Array(ref a)   => {
-        let mut err:HBEvalResult = Ok(());
-
-        for (index, e) in a.iter().enumerate() {
-          err = err.and(e.write_value(out));
-
-          if index < (a.len() - 1) && err.is_ok() {
-            err = err.and(write!(out, ","))
-          };
-
-          if err.is_err() {
-            break;
-          }
-        }
-        err
-      }
+      &Json::Array(_)       => write_values_list(self.values(), out),
       _  => Ok(()),
     }
   }
@@ -434,50 +525,12 @@ impl<'b> HBData for &'b str {
   fn iter<'a>(&'a self) -> HBIter<'a> { Box::new(None.into_iter()) }
 }
 
-struct FallbackToOptions<'a> {
-  data: &'a (HBData + 'a),
-  options: HashMap<&'a str, &'a (HBData+'a)>,
-}
-
-impl <'a> HBData for FallbackToOptions<'a> {
-  fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult {
-    self.data.write_value(out)
-  }
-
-  fn typed_node(&self) -> HBNodeType<&HBData> {
-    self.data.typed_node()
-  }
-
-  fn get_key(&self, key: &str) -> Option<&HBData> {
-    match self.data.get_key(key) {
-      v @ Some(_) => v,
-      None => {
-        self.options.get(key).map(|&v| v)
-      }
-    }
-  }
-
-  fn as_bool(&self) -> bool {
-    self.data.as_bool()
-  }
-
-  fn keys<'b>(&'b self) -> HBKeysIter<'b> {
-    Box::new(self.data.keys().chain(self.options.keys().map(|&s| s))) as HBKeysIter<'b>
-  }
-
-  fn values<'b>(&'b self) -> HBValuesIter<'b> {
-    Box::new(self.iter().map(|(_, v)| v as &'b HBData))
-  }
-
-  fn iter<'b>(&'b self) -> HBIter<'b> {
-    Box::new(self.data.iter().chain(self.options.iter().map(|(&s, &v)| (s, v)))) as HBIter<'b>
-  }
-}
-
 pub type HelperFunction = Box<Fn(&[&HBData], &HelperOptions, &mut SafeWriting, &EvalContext) -> HBEvalResult>;
 
 pub struct Helper {
   helper_func: HelperFunction,
+  // builtin helpers of `EvalContext::default()`, precompiled blocks render them
+  builtin: bool,
 }
 
 pub type HelperOptionsByName<'a> = HashMap<&'a String, &'a (HBData + 'a)>;
@@ -647,7 +700,11 @@ impl <'a> HelperOptions<'a> {
 
 impl Helper {
   pub fn new_with_function(f: HelperFunction) -> Helper {
-    Helper { helper_func: f }
+    Helper { helper_func: f, builtin: false }
+  }
+
+  fn builtin(f: HelperFunction) -> Helper {
+    Helper { helper_func: f, builtin: true }
   }
 
   fn build_param_vec<'a, 'b>(
@@ -745,23 +802,28 @@ pub struct EvalContext {
   /// mustache compatibility flag, to enable recursive lookups
   pub compat: bool,
   falsy: Json,
+  // none for child contexts, that use their parent escaper
+  escaper: Option<Box<Escaper>>,
+  parent: Option<Rc<EvalContext>>,
 }
 
 impl Default for EvalContext {
   fn default() -> EvalContext {
     let mut helpers = HashMap::new();
 
-    helpers.insert("each".to_string(),   Helper::new_with_function(Box::new(::helpers_builtins::each_helper)));
-    helpers.insert("if".to_string(),     Helper::new_with_function(Box::new(::helpers_builtins::if_helper)));
-    helpers.insert("unless".to_string(), Helper::new_with_function(Box::new(::helpers_builtins::unless_helper)));
-    helpers.insert("lookup".to_string(), Helper::new_with_function(Box::new(::helpers_builtins::lookup_helper)));
-    helpers.insert("with".to_string(),   Helper::new_with_function(Box::new(::helpers_builtins::with_helper)));
+    helpers.insert("each".to_string(),   Helper::builtin(Box::new(::helpers_builtins::each_helper)));
+    helpers.insert("if".to_string(),     Helper::builtin(Box::new(::helpers_builtins::if_helper)));
+    helpers.insert("unless".to_string(), Helper::builtin(Box::new(::helpers_builtins::unless_helper)));
+    helpers.insert("lookup".to_string(), Helper::builtin(Box::new(::helpers_builtins::lookup_helper)));
+    helpers.insert("with".to_string(),   Helper::builtin(Box::new(::helpers_builtins::with_helper)));
 
     EvalContext {
       partials: Default::default(),
       helpers: helpers,
       compat: false,
       falsy: Json::Null,
+      escaper: Some(Box::new(HTMLEscaper)),
+      parent: None,
     }
   }
 }
@@ -816,7 +878,7 @@ impl EvalContext {
     Default::default()
   }
 
-  /// Map of registered partials
+  /// partials registered in this context, without the parent ones
   pub fn partials(&self) -> &HashMap<String, Template> {
     return &self.partials;
   }
@@ -826,9 +888,58 @@ impl EvalContext {
     self.partials.insert(name, t);
   }
 
+  /// renders the registered template `name`, pages and partials share the
+  /// same namespace, so that a page and its partials are registered together.
+  ///
+  /// Errors are prefixed with the template name.
+  ///
+  /// ```
+  /// use rumblebars::EvalContext;
+  ///
+  /// let mut context = EvalContext::new();
+  /// context.register_partial("header".to_string(), "<h1>{{.}}</h1>".parse().unwrap());
+  /// context.register_partial("page".to_string(), "{{> header}}<p>welcome</p>".parse().unwrap());
+  ///
+  /// let mut out = Vec::new();
+  /// context.render("page", &"Home", &mut out).unwrap();
+  /// assert_eq!(String::from_utf8(out).unwrap(), "<h1>Home</h1><p>welcome</p>");
+  ///
+  /// assert!(context.render("missing", &"", &mut Vec::new()).is_err());
+  /// ```
+  pub fn render(&self, name: &str, data: &HBData, out: &mut io::Write) -> HBEvalResult {
+    match self.partial_with_name(name) {
+      Some(template) => eval(template, data, out, self).map_err(|e| {
+        io::Error::new(e.kind(), format!("template ‘{}’: {}", name, e))
+      }),
+      None => Err(io::Error::new(io::ErrorKind::NotFound, format!("no template named ‘{}’", name))),
+    }
+  }
+
+  /// renders the registered template `name` into a string, see `render()`
+  pub fn render_to_string(&self, name: &str, data: &HBData) -> io::Result<String> {
+    let mut s = String::new();
+    {
+      let mut fmt_writer = FmtWriter::new(&mut s);
+      try!(self.render(name, data, &mut fmt_writer));
+      try!(fmt_writer.finish());
+    }
+    Ok(s)
+  }
+
+  /// data paths, helpers and partials the registered template `name`
+  /// references, none if it is not registered, see `analyze()`
+  pub fn analyze(&self, name: &str) -> Option<Analysis> {
+    self.partial_with_name(name).map(|template| analyze(&template.entries, self))
+  }
+
+  /// removes a partial from the evaluation context
+  pub fn unregister_partial(&mut self, name: &str) -> Option<Template> {
+    self.partials.remove(name)
+  }
+
   /// fetch a registered partial by name
   pub fn partial_with_name(&self, name: &str) -> Option<&Template> {
-    return self.partials.get(name);
+    self.partials.get(name).or_else(|| self.parent.as_ref().and_then(|p| p.partial_with_name(name)))
   }
 
 
@@ -839,12 +950,73 @@ impl EvalContext {
 
   /// fetch a registered helper by name
   pub fn helper_with_name(&self, name: &str) -> Option<&Helper> {
-    return self.helpers.get(name);
+    self.helpers.get(name).or_else(|| self.parent.as_ref().and_then(|p| p.helper_with_name(name)))
   }
 
   /// true if a helper with given name is registered
   pub fn has_helper_with_name(&self, name: &str) -> bool {
-    return self.helpers.contains_key(name);
+    self.helper_with_name(name).is_some()
+  }
+
+  /// true if the helper with given name is a builtin one, not replaced by a
+  /// registered helper
+  pub fn has_builtin_helper(&self, name: &str) -> bool {
+    self.helper_with_name(name).map_or(false, |h| h.builtin)
+  }
+
+  /// sets the escaping strategy of `{{expression}}` values, html escaping by default
+  pub fn set_escaper(&mut self, escaper: Box<Escaper>) {
+    self.escaper = Some(escaper);
+  }
+
+  /// escaping strategy used for evaluation
+  pub fn escaper(&self) -> &Escaper {
+    match (&self.escaper, &self.parent) {
+      (&Some(ref escaper), _) => &**escaper,
+      (&None, &Some(ref parent)) => parent.escaper(),
+      (&None, &None) => panic!("Should not reach: root evaluation contexts always have an escaper"),
+    }
+  }
+
+  /// Context that inherits helpers, partials and escaper of `parent`, without
+  /// copying them. Its own registrations extend or shadow the parent ones,
+  /// `compat` is copied from the parent.
+  ///
+  /// The parent is thread-local: helpers and escapers are neither `Send` nor
+  /// `Sync`, so contexts can't be shared across threads. Servers rendering on
+  /// several threads keep a parent per thread, as in a `thread_local!`.
+  ///
+  /// ```
+  /// use std::rc::Rc;
+  /// use rumblebars::{Template, EvalContext, HBData};
+  ///
+  /// let mut shared = EvalContext::new();
+  /// shared.register_partial("form".to_string(), "<input value=\"{{csrf_token}}\">".parse().unwrap());
+  /// let shared = Rc::new(shared);
+  ///
+  /// // per request
+  /// let token = "a1b2".to_string();
+  /// let mut context = EvalContext::child(shared.clone());
+  /// context.register_helper("csrf_token".to_string(), Box::new(move |_, _, out, _| token.write_value(out)));
+  ///
+  /// let mut buf = Vec::new();
+  /// Template::new("{{> form}}").unwrap().eval(&"", &mut buf, &context).unwrap();
+  /// assert_eq!(String::from_utf8(buf).unwrap(), "<input value=\"a1b2\">");
+  /// ```
+  pub fn child(parent: Rc<EvalContext>) -> EvalContext {
+    EvalContext {
+      partials: HashMap::new(),
+      helpers: HashMap::new(),
+      compat: parent.compat,
+      falsy: Json::Null,
+      escaper: None,
+      parent: Some(parent),
+    }
+  }
+
+  /// context inherited by a child context
+  pub fn parent(&self) -> Option<&Rc<EvalContext>> {
+    self.parent.as_ref()
   }
 }
 
@@ -855,10 +1027,95 @@ pub fn eval(template: &Template, data: &HBData, out: &mut io::Write, eval_contex
   globals.insert("@root", data);
   globals.insert("@level", &log);
 
-  let mut html_safe = HTMLSafeWriter::new(out);
-  let mut safe_writer = SafeWriting::Safe(&mut html_safe);
+  let mut escape_writer = EscapeWriter::new(out, eval_context.escaper());
+  try!(eval_with_globals(&template.entries, data, &mut SafeWriting::Safe(&mut escape_writer), eval_context, &globals, &vec![data], None));
 
-  eval_with_globals(&template.entries, data, &mut safe_writer, eval_context, &globals, &vec![data], None)
+  escape_writer.finish()
+}
+
+/// Evaluates a template into a `fmt::Write`. See [`Template`](struct.Template.html) docs.
+pub fn eval_fmt(template: &Template, data: &HBData, out: &mut fmt::Write, eval_context: &EvalContext) -> HBEvalResult {
+  let mut fmt_writer = FmtWriter::new(out);
+  try!(eval(template, data, &mut fmt_writer, eval_context));
+  fmt_writer.finish()
+}
+
+// writes the output of an expression without block
+fn eval_expression<'a>(exp: &'a HBExpression, data: &'a HBData, context_stack: &Vec<&'a HBData>, global_data: &HashMap<&str, &'a HBData>, eval_context: &'a EvalContext, out: &mut SafeWriting, indent: Option<String>) -> HBEvalResult {
+  let &HBExpression{ref base, ref params, ref options, ref render_options, ..} = exp;
+
+  match (base.first(), base.len()) {
+    (Some(ref single), 1) if eval_context.has_helper_with_name(&single) => {
+      let helper = eval_context.helper_with_name(&single).unwrap();
+      if render_options.escape {
+        IndentWriter::with_indent(indent, out, |w| {
+          helper.call_fn(data, &params, &options, w, eval_context, context_stack, global_data)
+        })
+      } else {
+        IndentWriter::with_indent(indent, &mut out.into_unsafe(), |w| {
+          helper.call_fn(data, &params, &options, w, eval_context, context_stack, global_data)
+        })
+      }
+    },
+    _ => match value_for_key_path_in_context(data, base, context_stack, global_data, eval_context.compat) {
+      Some(v) if v.as_lambda().is_some() => {
+        // lambda output is rendered unescaped, then escaped as any value
+        let rendered = try!(call_lambda(v.as_lambda().unwrap(), None, data, context_stack, global_data, eval_context, false));
+        if render_options.escape {
+          IndentWriter::with_indent(indent, out, |w| {
+            w.write_all(rendered.as_bytes())
+          })
+        } else {
+          IndentWriter::with_indent(indent, &mut out.into_unsafe(), |w| {
+            w.write_all(rendered.as_bytes())
+          })
+        }
+      },
+      Some(v) => match v.typed_node() {
+        HBNodeType::Leaf(_) | HBNodeType::Array(_)=> {
+          if render_options.escape {
+            IndentWriter::with_indent(indent, out, |w| {
+              v.write_value(w)
+            })
+          } else {
+            IndentWriter::with_indent(indent, &mut out.into_unsafe(), |w| {
+              v.write_value(w)
+            })
+          }
+        },
+        _ => Ok(()),
+      },
+      None => Ok(()),
+    }
+  }
+}
+
+// renders a template string returned by a lambda, with the context of the lambda expression
+fn render_lambda_template(source: &str, data: &HBData, context_stack: &Vec<&HBData>, global_data: &HashMap<&str, &HBData>, eval_context: &EvalContext, escape: bool) -> io::Result<String> {
+  let template = try!(parse(source).map_err(|(err, msg)| {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid template returned by lambda, {:?}: {}", err, msg.unwrap_or(String::new())))
+  }));
+
+  let mut buf = Vec::new();
+  {
+    let eval_result = if escape {
+      let mut escape_writer = EscapeWriter::new(&mut buf, eval_context.escaper());
+      let result = eval_with_globals(&template.entries, data, &mut SafeWriting::Safe(&mut escape_writer), eval_context, global_data, context_stack, None);
+      result.and_then(|_| escape_writer.finish())
+    } else {
+      eval_with_globals(&template.entries, data, &mut SafeWriting::Unsafe(&mut buf), eval_context, global_data, context_stack, None)
+    };
+    try!(eval_result);
+  }
+
+  String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn call_lambda(lambda: &Lambda, section: Option<&str>, data: &HBData, context_stack: &Vec<&HBData>, global_data: &HashMap<&str, &HBData>, eval_context: &EvalContext, escape: bool) -> io::Result<String> {
+  let render = |source: &str| render_lambda_template(source, data, context_stack, global_data, eval_context, true);
+  let output = try!(lambda.call(section, &render));
+
+  render_lambda_template(&output, data, context_stack, global_data, eval_context, escape)
 }
 
 struct RenderEntry<'a> {
@@ -922,12 +1179,18 @@ pub fn eval_with_globals<'a: 'b, 'b: 'c, 'c>(entries: &'a Entries, data: &'a HBD
   // used for storage of partials optional keys, computed at evaluation
   // this can leak, but probably not in relevant cases.
   let mut partial_options_current_index = 0;
-  let mut partial_options_contexts = HashMap::<usize, FallbackToOptions>::new();
+  let mut partial_options_contexts = HashMap::<usize, Layers>::new();
+  // partials being rendered, errors are prefixed with the innermost one
+  let mut partial_names: Vec<String> = Vec::new();
 
   while stack.len() > 0 {
     let w_ok = if let Some(stack_entry) = stack.pop() {
       match stack_entry {
-        CleanUpPartialContext(ref index) => { partial_options_contexts.remove(index); Ok(()) },
+        CleanUpPartialContext(ref index) => {
+          partial_options_contexts.remove(index);
+          partial_names.pop();
+          Ok(())
+        },
         ContextIterControlPoint(ref control) => if let Some(next) = control.data_iter.borrow_mut().next() {
           stack.push(ContextIterControlPoint(IterationControl {
             entries: control.entries,
@@ -950,14 +1213,16 @@ pub fn eval_with_globals<'a: 'b, 'b: 'c, 'c>(entries: &'a Entries, data: &'a HBD
 
           match **flow_entry.entry {
             HBEntry::Raw(ref s) => {
-              IndentWriter::with_indent(flow_entry.indent.clone(), &mut out.into_unsafe(), &|w| {
+              IndentWriter::with_indent(flow_entry.indent.clone(), &mut out.into_unsafe(), |w| {
                 w.write_all(&s.as_bytes())
               })
             },
+            HBEntry::Comment(_) => Ok(()),
             HBEntry::Partial(ref exp) => {
               match exp.base.first() {
-                Some(ref single) if exp.base.len() == 1 => {
-                  match eval_context.partial_with_name(&single) {
+                Some(_) => {
+                  // partials are looked up by their name as written, as `shared/header`
+                  match eval_context.partial_with_name(&exp.partial_name()) {
                     Some(ref t) => {
                       let c_ctxt = if let Some(&HBValHolder::Path(ref p)) = exp.params.get(0) {
                         value_for_key_path_in_context(ctxt, p, &flow_entry.stack, global_data, eval_context.compat).unwrap_or(ctxt)
@@ -987,10 +1252,10 @@ pub fn eval_with_globals<'a: 'b, 'b: 'c, 'c>(entries: &'a Entries, data: &'a HBD
                         // outside it's current scope
                         partial_options_contexts.insert(
                           partial_options_current_index,
-                          FallbackToOptions { data: c_ctxt, options: options_contexts }
+                          Layers::new().layer(c_ctxt).owned_layer(options_contexts)
                         );
                         unsafe { ::std::mem::transmute(
-                          partial_options_contexts.get(&partial_options_current_index).map(|f| f as &HBData).unwrap()
+                          partial_options_contexts.get(&partial_options_current_index).map(|layers| layers as &HBData).unwrap()
                         ) }
                       } else {
                         c_ctxt
@@ -1005,6 +1270,7 @@ pub fn eval_with_globals<'a: 'b, 'b: 'c, 'c>(entries: &'a Entries, data: &'a HBD
 
                       stack.push(CleanUpPartialContext(partial_options_current_index));
                       partial_options_current_index += 1;
+                      partial_names.push(exp.partial_name());
 
                       for ref e in t.entries.iter().rev() {
                         stack.push(FlowEntry(RenderEntry::new(
@@ -1018,46 +1284,23 @@ pub fn eval_with_globals<'a: 'b, 'b: 'c, 'c>(entries: &'a Entries, data: &'a HBD
                     _ => Ok(())
                   }
                 }
-                Some(_) => panic!("invalid partial name '{}'", exp.path()),
                 None => panic!("invalid empty string to retrieve partial by name"),
               }
             },
 
-            HBEntry::Eval(HBExpression{ref base, ref params, ref options, ref render_options, block: None, else_block: None}) => {
-              match (base.first(), base.len()) {
-                (Some(ref single), 1) if eval_context.has_helper_with_name(&single) => {
-                  let helper = eval_context.helper_with_name(&single).unwrap();
-                  if render_options.escape {
-                    IndentWriter::with_indent(flow_entry.indent.clone(), out, &|w| {
-                      helper.call_fn(ctxt, &params, &options, w, eval_context, &flow_entry.stack, global_data)
-                    })
-                  } else {
-                    IndentWriter::with_indent(flow_entry.indent.clone(), &mut out.into_unsafe(), &|w| {
-                      helper.call_fn(ctxt, &params, &options, w, eval_context, &flow_entry.stack, global_data)
-                    })
-                  }
+            HBEntry::Eval(ref exp @ HBExpression{block: None, else_block: None, ..}) => {
+              match exp.render_options.context {
+                Some(context) if exp.render_options.escape && context != HTMLContext::Text => {
+                  // contextual autoescaping needs the whole value
+                  let mut buf = Vec::new();
+                  try!(eval_expression(exp, ctxt, &flow_entry.stack, global_data, eval_context, &mut SafeWriting::Unsafe(&mut buf), flow_entry.indent.clone()));
+                  escape_in_context(context, &String::from_utf8_lossy(&buf), &mut out.into_unsafe())
                 },
-                _ => match value_for_key_path_in_context(ctxt, base, &flow_entry.stack, global_data, eval_context.compat) {
-                  Some(v) => match v.typed_node() {
-                    HBNodeType::Leaf(_) | HBNodeType::Array(_)=> {
-                      if render_options.escape {
-                        IndentWriter::with_indent(flow_entry.indent.clone(), out, &|w| {
-                          v.write_value(w)
-                        })
-                      } else {
-                        IndentWriter::with_indent(flow_entry.indent.clone(), &mut out.into_unsafe(), &|w| {
-                          v.write_value(w)
-                        })
-                      }
-                    },
-                    _ => Ok(()),
-                  },
-                  None => Ok(()),
-                }
+                _ => eval_expression(exp, ctxt, &flow_entry.stack, global_data, eval_context, out, flow_entry.indent.clone()),
               }
             },
 
-            HBEntry::Eval(HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block}) => {
+            HBEntry::Eval(HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block, ref block_source, ..}) => {
               render_options.escape; // only suppress unused warning
               match (base.first(), base.len()) {
                 (Some(ref single), 1) if eval_context.has_helper_with_name(&single) => {
@@ -1092,6 +1335,16 @@ pub fn eval_with_globals<'a: 'b, 'b: 'c, 'c>(entries: &'a Entries, data: &'a HBD
                   let c_ctxt = value_for_key_path_in_context(ctxt, base, &flow_entry.stack, global_data, eval_context.compat);
 
                   match (c_ctxt.unwrap_or(&eval_context.falsy), block) {
+                    (c, &Some(_)) if c.as_lambda().is_some() => {
+                      // lambda sections get the raw block source, inverted ones never render
+                      if !render_options.inverse {
+                        let section = block_source.as_ref().map(|s| &s[..]).unwrap_or("");
+                        let rendered = try!(call_lambda(c.as_lambda().unwrap(), Some(section), ctxt, &flow_entry.stack, global_data, eval_context, true));
+                        try!(IndentWriter::with_indent(flow_entry.indent.clone(), &mut out.into_unsafe(), |w| {
+                          w.write_all(rendered.as_bytes())
+                        }));
+                      }
+                    },
                     (c, &Some(ref block_found)) => {
                       match c.typed_node() {
                         HBNodeType::Branch(_) | HBNodeType::Leaf(_) | HBNodeType::Null => {
@@ -1162,7 +1415,12 @@ pub fn eval_with_globals<'a: 'b, 'b: 'c, 'c>(entries: &'a Entries, data: &'a HBD
     } else {
       Ok(())
     };
-    if w_ok.is_err() { return w_ok };
+    if let Err(e) = w_ok {
+      return Err(match partial_names.last() {
+        Some(name) => io::Error::new(e.kind(), format!("template ‘{}’: {}", name, e)),
+        None => e,
+      });
+    }
   }
 
   Ok(())
@@ -1217,6 +1475,48 @@ mod tests {
     assert_eq!(String::from_utf8(buf).unwrap(), "1");
   }
 
+  #[test]
+  fn writers_split_characters() {
+    use std::io::Write;
+    use super::IndentWriter;
+
+    let text = "é<\nà>";
+    let mut buf: Vec<u8> = Vec::new();
+
+    {
+      let mut safe_writer = HTMLSafeWriter::new(&mut buf);
+      let mut indenter = IndentWriter {w: &mut safe_writer, indent: Some("  ".to_string())};
+      for byte in text.as_bytes().chunks(1) {
+        indenter.write_all(byte).unwrap();
+      }
+    }
+
+    assert_eq!(String::from_utf8(buf).unwrap(), "é&lt;\n  à&gt;");
+  }
+
+  #[test]
+  fn fmt_writer_split_characters() {
+    use std::io::Write;
+    use super::FmtWriter;
+
+    let text = "é<à";
+    let mut s = String::new();
+
+    {
+      let mut fmt_writer = FmtWriter::new(&mut s);
+      for byte in text.as_bytes().chunks(1) {
+        fmt_writer.write_all(byte).unwrap();
+      }
+      fmt_writer.finish().unwrap();
+    }
+
+    assert_eq!(s, text);
+
+    let mut fmt_writer = FmtWriter::new(&mut s);
+    fmt_writer.write_all(&text.as_bytes()[..1]).unwrap();
+    assert!(fmt_writer.finish().is_err());
+  }
+
   #[test]
   fn fetch_key_value_level1() {
     let json = Json::from_str(r##"{"a": {"b": 1}}"##).unwrap();
@@ -0,0 +1,142 @@
+use std::fmt;
+
+use parse::{Entries, HBEntry, HBComment, ParseFailure, parse_detailed};
+use printer::Printer;
+use visit::Visitor;
+
+/// Formats handlebars templates without changing what they render.
+///
+/// Expressions are written without inner spacing (`{{ foo }}` becomes `{{foo}}`),
+/// block tags and comments that start a line are put alone on their line and
+/// indented by their nesting level, unless they follow another tag line.
+/// Comments are kept as written.
+///
+/// Block contents are indented only through these tag lines, that are dropped
+/// on rendering: text lines are left untouched, their indentation being part
+/// of the output, as is the indentation of standalone partials, that indents
+/// their content.
+///
+/// Formatted templates are parsed back and checked to render the same output,
+/// the block source given to lambdas aside, and to keep every comment.
+///
+/// # Examples
+///
+/// ```
+/// use rumblebars::Formatter;
+///
+/// let source = "<ul>\n{{#each  items }}\n<li>{{ name }}</li>\n{{/each}}\n</ul>";
+/// let formatted = Formatter::new().format(source).unwrap();
+///
+/// assert_eq!(formatted, "<ul>\n{{#each items}}\n<li>{{name}}</li>\n{{/each}}\n</ul>");
+/// ```
+pub struct Formatter {
+  indent: String,
+}
+
+/// Failure to format a template
+#[derive(Debug)]
+pub enum FormatError {
+  Parse(ParseFailure),
+  /// the formatted template would not render the same output
+  Changed,
+  /// the formatted template would lose comments
+  Dropped,
+}
+
+impl fmt::Display for FormatError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      FormatError::Parse(ref failure) => write!(f, "{}", failure),
+      FormatError::Changed => write!(f, "formatting would change the rendered output"),
+      FormatError::Dropped => write!(f, "formatting would drop comments"),
+    }
+  }
+}
+
+impl Default for Formatter {
+  fn default() -> Formatter {
+    Formatter { indent: "  ".to_string() }
+  }
+}
+
+impl Formatter {
+  /// formatter indenting blocks by two spaces
+  pub fn new() -> Formatter {
+    Default::default()
+  }
+
+  /// sets the indentation of each block level
+  pub fn indent(mut self, indent: &str) -> Formatter {
+    self.indent = indent.to_string();
+    self
+  }
+
+  /// formatted `source`
+  pub fn format(&self, source: &str) -> Result<String, FormatError> {
+    let mut template = try!(parse_detailed(source).map_err(FormatError::Parse));
+
+    let mut printer = Printer::new(Some(self.indent.clone()));
+    printer.entries(&template.entries);
+    let formatted = printer.out;
+
+    let mut reparsed = try!(parse_detailed(&formatted).map_err(|_| FormatError::Changed));
+    if comments(&template.entries) != comments(&reparsed.entries) {
+      return Err(FormatError::Dropped);
+    }
+    if rendered_tree(&mut template.entries) != rendered_tree(&mut reparsed.entries) {
+      return Err(FormatError::Changed);
+    }
+
+    Ok(formatted)
+  }
+
+  /// true when `source` is already formatted
+  pub fn is_formatted(&self, source: &str) -> Result<bool, FormatError> {
+    self.format(source).map(|formatted| formatted == source)
+  }
+}
+
+struct Comments(Vec<String>);
+
+impl Visitor for Comments {
+  fn visit_comment(&mut self, comment: &HBComment) {
+    self.0.push(comment.source.clone());
+  }
+}
+
+// comments of `entries`, as written
+fn comments(entries: &Entries) -> Vec<String> {
+  let mut comments = Comments(vec![]);
+  comments.visit_entries(entries);
+  comments.0
+}
+
+// syntax tree without what does not change rendering: positions, block
+// sources, block tags and comments indentation
+fn rendered_tree(entries: &mut Entries) -> String {
+  fn clear(entries: &mut Entries) {
+    for entry in entries.iter_mut() {
+      match **entry {
+        HBEntry::Eval(ref mut exp) => {
+          exp.position = Default::default();
+          if exp.block.is_some() {
+            exp.block_source = None;
+            exp.render_options.indent = None;
+          }
+          for block in exp.block.iter_mut().chain(exp.else_block.iter_mut()) {
+            clear(block);
+          }
+        },
+        HBEntry::Partial(ref mut exp) => exp.position = Default::default(),
+        HBEntry::Comment(ref mut comment) => {
+          comment.position = Default::default();
+          comment.render_options.indent = None;
+        },
+        HBEntry::Raw(_) => (),
+      }
+    }
+  }
+
+  clear(entries);
+  format!("{:?}", entries)
+}
@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use parse::{Entries, HBExpression};
+use eval::EvalContext;
+use visit::{Visitor, walk_expression};
+
+/// Include graph of the templates registered in an `EvalContext`, from their
+/// `{{> partial}}` tags.
+///
+/// Includes inside a block, such as `{{#if}}` or `{{#each}}`, are guarded: the
+/// block can stop the recursion. Cycles are reported unless each of their
+/// loops goes through a guarded include, and one of their templates is
+/// flagged with `allow_recursion()`.
+///
+/// # Examples
+///
+/// ```
+/// use rumblebars::{parse, EvalContext, PartialGraph};
+///
+/// let mut context = EvalContext::new();
+/// context.register_partial("page".to_string(), parse("{{> header}}{{#each tree}}{{> node}}{{/each}}").unwrap());
+/// context.register_partial("node".to_string(), parse("{{name}}{{#each children}}{{> node}}{{/each}}").unwrap());
+/// context.register_partial("header".to_string(), parse("{{> header}}{{> logo}}").unwrap());
+///
+/// let graph = PartialGraph::new(&context).allow_recursion("node");
+///
+/// assert_eq!(graph.cycles().len(), 1);
+/// assert_eq!(graph.cycles()[0].templates, vec!["header"]);
+/// assert_eq!(graph.missing(), vec![("header", "logo")]);
+/// ```
+pub struct PartialGraph {
+  // included partials of each template, guarded when all includes are
+  includes: BTreeMap<String, BTreeMap<String, bool>>,
+  allowed: BTreeSet<String>,
+}
+
+/// Templates including each other, directly or not
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle {
+  /// sorted by name
+  pub templates: Vec<String>,
+  /// every loop goes through an include guarded by a block
+  pub guarded: bool,
+}
+
+impl PartialGraph {
+  /// graph of the templates registered in `eval_context`, with the ones of
+  /// parent contexts they include
+  pub fn new(eval_context: &EvalContext) -> PartialGraph {
+    let mut includes = BTreeMap::new();
+    let mut pending: Vec<String> = eval_context.partials().keys().cloned().collect();
+
+    while let Some(name) = pending.pop() {
+      if includes.contains_key(&name) {
+        continue;
+      }
+      let targets = match eval_context.partial_with_name(&name) {
+        Some(template) => template_includes(&template.entries),
+        None => continue,
+      };
+
+      for target in targets.keys() {
+        if eval_context.partial_with_name(target).is_some() {
+          pending.push(target.clone());
+        }
+      }
+      includes.insert(name, targets);
+    }
+
+    PartialGraph { includes: includes, allowed: BTreeSet::new() }
+  }
+
+  /// flags the recursion of the template `name` as intentional, its cycles are
+  /// not reported when guarded
+  pub fn allow_recursion(mut self, name: &str) -> PartialGraph {
+    self.allowed.insert(name.to_string());
+    self
+  }
+
+  /// templates of the graph, sorted by name
+  pub fn templates(&self) -> Vec<&str> {
+    self.includes.keys().map(|name| &name[..]).collect()
+  }
+
+  /// partials the template `name` includes, sorted by name
+  pub fn includes(&self, name: &str) -> Vec<&str> {
+    self.includes.get(name).map(|targets| targets.keys().map(|target| &target[..]).collect()).unwrap_or_default()
+  }
+
+  /// included partials that are not registered, with the template including them
+  pub fn missing(&self) -> Vec<(&str, &str)> {
+    let mut missing = Vec::new();
+    for (name, targets) in self.includes.iter() {
+      for target in targets.keys().filter(|target| !self.includes.contains_key(*target)) {
+        missing.push((&name[..], &target[..]));
+      }
+    }
+    missing
+  }
+
+  /// cycles that can recurse forever at runtime, sorted by their first template
+  pub fn cycles(&self) -> Vec<Cycle> {
+    self.all_cycles().into_iter().filter(|cycle| {
+      !(cycle.guarded && cycle.templates.iter().any(|name| self.allowed.contains(name)))
+    }).collect()
+  }
+
+  fn all_cycles(&self) -> Vec<Cycle> {
+    let names: Vec<&String> = self.includes.keys().collect();
+
+    let mut cycles: Vec<Cycle> = cyclic_components(&self.adjacency(&names, false)).into_iter().map(|component| {
+      let members: BTreeSet<usize> = component.iter().cloned().collect();
+      // loops left without guarded includes
+      let unguarded: Vec<Vec<usize>> = self.adjacency(&names, true).into_iter().enumerate().map(|(i, targets)| {
+        if members.contains(&i) { targets.into_iter().filter(|t| members.contains(t)).collect() } else { Vec::new() }
+      }).collect();
+
+      Cycle {
+        templates: component.iter().map(|&i| names[i].clone()).collect::<BTreeSet<_>>().into_iter().collect(),
+        guarded: cyclic_components(&unguarded).is_empty(),
+      }
+    }).collect();
+
+    cycles.sort_by(|a, b| a.templates.cmp(&b.templates));
+    cycles
+  }
+
+  // includes by template index, the guarded ones left out if `unguarded`
+  fn adjacency(&self, names: &[&String], unguarded: bool) -> Vec<Vec<usize>> {
+    names.iter().map(|name| {
+      self.includes[*name].iter()
+        .filter(|&(_, &guarded)| !(unguarded && guarded))
+        .filter_map(|(target, _)| names.iter().position(|n| *n == target))
+        .collect()
+    }).collect()
+  }
+
+  /// Graphviz source of the graph: guarded includes are dashed, missing
+  /// partials and the templates of reported cycles are red.
+  pub fn to_dot(&self) -> String {
+    let in_cycle: BTreeSet<String> = self.cycles().into_iter().flat_map(|cycle| cycle.templates.into_iter()).collect();
+    let missing: BTreeSet<&str> = self.missing().into_iter().map(|(_, target)| target).collect();
+
+    let mut dot = "digraph partials {\n".to_string();
+    for name in self.includes.keys() {
+      if in_cycle.contains(name) {
+        dot.push_str(&format!("  {} [color=red];\n", dot_id(name)));
+      } else {
+        dot.push_str(&format!("  {};\n", dot_id(name)));
+      }
+    }
+    for name in missing.iter() {
+      dot.push_str(&format!("  {} [color=red, style=dashed];\n", dot_id(name)));
+    }
+    for (name, targets) in self.includes.iter() {
+      for (target, &guarded) in targets.iter() {
+        let style = if guarded { " [style=dashed]" } else { "" };
+        dot.push_str(&format!("  {} -> {}{};\n", dot_id(name), dot_id(target), style));
+      }
+    }
+    dot.push_str("}\n");
+
+    dot
+  }
+}
+
+fn dot_id(name: &str) -> String {
+  format!("\"{}\"", name.replace("\\", "\\\\").replace("\"", "\\\""))
+}
+
+fn template_includes(entries: &Entries) -> BTreeMap<String, bool> {
+  let mut collector = IncludeCollector { includes: BTreeMap::new(), depth: 0 };
+  collector.visit_entries(entries);
+  collector.includes
+}
+
+struct IncludeCollector {
+  includes: BTreeMap<String, bool>,
+  // enclosing blocks
+  depth: usize,
+}
+
+impl Visitor for IncludeCollector {
+  fn visit_expression(&mut self, exp: &HBExpression) {
+    let block = exp.block.is_some();
+    if block {
+      self.depth += 1;
+    }
+    walk_expression(self, exp);
+    if block {
+      self.depth -= 1;
+    }
+  }
+
+  fn visit_partial(&mut self, exp: &HBExpression) {
+    let guarded = self.depth > 0;
+    let entry = self.includes.entry(exp.partial_name()).or_insert(guarded);
+    *entry = *entry && guarded;
+  }
+}
+
+// strongly connected components with a loop, with Tarjan's algorithm
+fn cyclic_components(adjacency: &[Vec<usize>]) -> Vec<Vec<usize>> {
+  let mut tarjan = Tarjan {
+    adjacency: adjacency,
+    index: vec![None; adjacency.len()],
+    low: vec![0; adjacency.len()],
+    on_stack: vec![false; adjacency.len()],
+    stack: Vec::new(),
+    next: 0,
+    components: Vec::new(),
+  };
+
+  for v in 0..adjacency.len() {
+    if tarjan.index[v].is_none() {
+      tarjan.visit(v);
+    }
+  }
+
+  tarjan.components.into_iter().filter(|component| {
+    component.len() > 1 || adjacency[component[0]].contains(&component[0])
+  }).collect()
+}
+
+struct Tarjan<'a> {
+  adjacency: &'a [Vec<usize>],
+  index: Vec<Option<usize>>,
+  low: Vec<usize>,
+  on_stack: Vec<bool>,
+  stack: Vec<usize>,
+  next: usize,
+  components: Vec<Vec<usize>>,
+}
+
+impl<'a> Tarjan<'a> {
+  fn visit(&mut self, v: usize) {
+    self.index[v] = Some(self.next);
+    self.low[v] = self.next;
+    self.next += 1;
+    self.stack.push(v);
+    self.on_stack[v] = true;
+
+    let adjacency = self.adjacency;
+    for &w in adjacency[v].iter() {
+      match self.index[w] {
+        None => {
+          self.visit(w);
+          self.low[v] = ::std::cmp::min(self.low[v], self.low[w]);
+        },
+        Some(i) if self.on_stack[w] => self.low[v] = ::std::cmp::min(self.low[v], i),
+        _ => (),
+      }
+    }
+
+    if Some(self.low[v]) == self.index[v] {
+      let mut component = Vec::new();
+      while let Some(w) = self.stack.pop() {
+        self.on_stack[w] = false;
+        component.push(w);
+        if w == v {
+          break;
+        }
+      }
+      self.components.push(component);
+    }
+  }
+}
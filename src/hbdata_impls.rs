@@ -0,0 +1,257 @@
+use std::io::Write;
+use std::collections::{HashMap, BTreeMap};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use eval::{HBData, HBNodeType, HBEvalResult, HBKeysIter, HBValuesIter, HBIter, SafeWriting, write_values_list};
+use hbdata_lambda::Lambda;
+
+// scalars, truthiness follows the Json implementation
+
+macro_rules! leaf_hbdata {
+  ($($t: ty => |$v: ident| $truthy: expr),*) => ($(
+    impl HBData for $t {
+      fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult {
+        write!(out, "{}", self)
+      }
+
+      fn typed_node<'a>(&'a self) -> HBNodeType<&'a HBData> {
+        HBNodeType::Leaf(self as &HBData)
+      }
+
+      fn as_bool(&self) -> bool { let $v = *self; $truthy }
+
+      fn get_key<'a>(&'a self, _: &str) -> Option<&'a HBData> { None }
+      fn keys<'a>(&'a self) -> HBKeysIter<'a> { Box::new(None.into_iter()) }
+      fn values<'a>(&'a self) -> HBValuesIter<'a> { Box::new(None.into_iter()) }
+      fn iter<'a>(&'a self) -> HBIter<'a> { Box::new(None.into_iter()) }
+    }
+  )*)
+}
+
+leaf_hbdata!(
+  i8    => |i| i != 0,
+  i16   => |i| i != 0,
+  i32   => |i| i != 0,
+  i64   => |i| i != 0,
+  isize => |i| i != 0,
+  u8    => |u| u != 0,
+  u16   => |u| u != 0,
+  u32   => |u| u != 0,
+  u64   => |u| u != 0,
+  usize => |u| u != 0,
+  f32   => |f| f != 0.0 && !f.is_nan(),
+  f64   => |f| f != 0.0 && !f.is_nan(),
+  bool  => |b| b
+);
+
+impl HBData for () {
+  fn write_value(&self, _: &mut SafeWriting) -> HBEvalResult { Ok(()) }
+  fn typed_node<'a>(&'a self) -> HBNodeType<&'a HBData> { HBNodeType::Null }
+  fn as_bool(&self) -> bool { false }
+  fn get_key<'a>(&'a self, _: &str) -> Option<&'a HBData> { None }
+  fn keys<'a>(&'a self) -> HBKeysIter<'a> { Box::new(None.into_iter()) }
+  fn values<'a>(&'a self) -> HBValuesIter<'a> { Box::new(None.into_iter()) }
+  fn iter<'a>(&'a self) -> HBIter<'a> { Box::new(None.into_iter()) }
+}
+
+// None is rendered as null, Some is transparent
+
+impl<T: HBData> HBData for Option<T> {
+  fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult {
+    match self {
+      &Some(ref v) => v.write_value(out),
+      &None => Ok(()),
+    }
+  }
+
+  fn typed_node<'a>(&'a self) -> HBNodeType<&'a HBData> {
+    match self {
+      &Some(ref v) => v.typed_node(),
+      &None => HBNodeType::Null,
+    }
+  }
+
+  fn as_bool(&self) -> bool {
+    self.as_ref().map(|v| v.as_bool()).unwrap_or(false)
+  }
+
+  fn get_key<'a>(&'a self, key: &str) -> Option<&'a HBData> {
+    self.as_ref().and_then(|v| v.get_key(key))
+  }
+
+  fn keys<'a>(&'a self) -> HBKeysIter<'a> {
+    self.as_ref().map(|v| v.keys()).unwrap_or(Box::new(None.into_iter()))
+  }
+
+  fn values<'a>(&'a self) -> HBValuesIter<'a> {
+    self.as_ref().map(|v| v.values()).unwrap_or(Box::new(None.into_iter()))
+  }
+
+  fn iter<'a>(&'a self) -> HBIter<'a> {
+    self.as_ref().map(|v| v.iter()).unwrap_or(Box::new(None.into_iter()))
+  }
+
+  fn as_lambda(&self) -> Option<&Lambda> {
+    self.as_ref().and_then(|v| v.as_lambda())
+  }
+}
+
+// collections, behaves like Json arrays
+
+macro_rules! array_hbdata {
+  ($([$($generics: tt)*] $t: ty;)*) => ($(
+    impl<$($generics)*> HBData for $t {
+      fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult {
+        write_values_list(self.values(), out)
+      }
+
+      fn typed_node<'a>(&'a self) -> HBNodeType<&'a HBData> {
+        HBNodeType::Array(self as &HBData)
+      }
+
+      fn as_bool(&self) -> bool { !self.is_empty() }
+
+      fn get_key<'a>(&'a self, key: &str) -> Option<&'a HBData> {
+        key.parse::<usize>().ok().and_then(|index| self.get(index)).map(|v| v as &HBData)
+      }
+
+      fn keys<'a>(&'a self) -> HBKeysIter<'a> { Box::new(None.into_iter()) }
+
+      fn values<'a>(&'a self) -> HBValuesIter<'a> {
+        Box::new(self[..].iter().map(|v| v as &'a HBData))
+      }
+
+      fn iter<'a>(&'a self) -> HBIter<'a> { Box::new(None.into_iter()) }
+    }
+  )*)
+}
+
+array_hbdata!(
+  [T: HBData] Vec<T>;
+  ['b, T: HBData] &'b [T];
+);
+
+// string keyed maps, behaves like Json objects
+
+macro_rules! map_hbdata {
+  ($($t: ident),*) => ($(
+    impl<T: HBData> HBData for $t<String, T> {
+      fn write_value(&self, _: &mut SafeWriting) -> HBEvalResult { Ok(()) }
+
+      fn typed_node<'a>(&'a self) -> HBNodeType<&'a HBData> {
+        HBNodeType::Branch(self as &HBData)
+      }
+
+      fn as_bool(&self) -> bool { true }
+
+      fn get_key<'a>(&'a self, key: &str) -> Option<&'a HBData> {
+        self.get(key).map(|v| v as &HBData)
+      }
+
+      fn keys<'a>(&'a self) -> HBKeysIter<'a> {
+        Box::new(self.keys().map(|k| &k[..]))
+      }
+
+      fn values<'a>(&'a self) -> HBValuesIter<'a> {
+        Box::new(self.values().map(|v| v as &'a HBData))
+      }
+
+      fn iter<'a>(&'a self) -> HBIter<'a> {
+        Box::new(self.iter().map(|(k, v)| (&k[..], v as &'a HBData)))
+      }
+    }
+  )*)
+}
+
+map_hbdata!(HashMap, BTreeMap);
+
+// borrowed maps, as used for globals
+
+impl<'b> HBData for HashMap<&'b str, &'b (HBData + 'b)> {
+  fn write_value(&self, _: &mut SafeWriting) -> HBEvalResult { Ok(()) }
+
+  fn typed_node<'a>(&'a self) -> HBNodeType<&'a HBData> {
+    HBNodeType::Branch(self as &HBData)
+  }
+
+  fn as_bool(&self) -> bool { true }
+
+  fn get_key<'a>(&'a self, key: &str) -> Option<&'a HBData> {
+    self.get(key).map(|v| *v)
+  }
+
+  fn keys<'a>(&'a self) -> HBKeysIter<'a> {
+    Box::new(self.keys().map(|k| *k))
+  }
+
+  fn values<'a>(&'a self) -> HBValuesIter<'a> {
+    Box::new(self.values().map(|v| *v))
+  }
+
+  fn iter<'a>(&'a self) -> HBIter<'a> {
+    Box::new(self.iter().map(|(k, v)| (*k, *v)))
+  }
+}
+
+// smart pointers are transparent
+
+macro_rules! pointer_hbdata {
+  ($($t: ident),*) => ($(
+    impl<T: HBData + ?Sized> HBData for $t<T> {
+      fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult { (**self).write_value(out) }
+      fn typed_node<'a>(&'a self) -> HBNodeType<&'a HBData> { (**self).typed_node() }
+      fn as_bool(&self) -> bool { (**self).as_bool() }
+      fn get_key<'a>(&'a self, key: &str) -> Option<&'a HBData> { (**self).get_key(key) }
+      fn keys<'a>(&'a self) -> HBKeysIter<'a> { (**self).keys() }
+      fn values<'a>(&'a self) -> HBValuesIter<'a> { (**self).values() }
+      fn iter<'a>(&'a self) -> HBIter<'a> { (**self).iter() }
+      fn as_lambda(&self) -> Option<&Lambda> { (**self).as_lambda() }
+    }
+  )*)
+}
+
+pointer_hbdata!(Box, Rc, Arc);
+
+// tuples are fixed size arrays
+
+macro_rules! tuple_hbdata {
+  ($(($($t: ident : $idx: tt),+);)*) => ($(
+    impl<$($t: HBData),+> HBData for ($($t,)+) {
+      fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult {
+        write_values_list(self.values(), out)
+      }
+
+      fn typed_node<'a>(&'a self) -> HBNodeType<&'a HBData> {
+        HBNodeType::Array(self as &HBData)
+      }
+
+      fn as_bool(&self) -> bool { true }
+
+      fn get_key<'a>(&'a self, key: &str) -> Option<&'a HBData> {
+        match key {
+          $(stringify!($idx) => Some(&self.$idx as &HBData),)+
+          _ => None,
+        }
+      }
+
+      fn keys<'a>(&'a self) -> HBKeysIter<'a> { Box::new(None.into_iter()) }
+
+      fn values<'a>(&'a self) -> HBValuesIter<'a> {
+        let values: Vec<&'a HBData> = vec![$(&self.$idx as &HBData),+];
+        Box::new(values.into_iter())
+      }
+
+      fn iter<'a>(&'a self) -> HBIter<'a> { Box::new(None.into_iter()) }
+    }
+  )*)
+}
+
+tuple_hbdata!(
+  (A: 0);
+  (A: 0, B: 1);
+  (A: 0, B: 1, C: 2);
+  (A: 0, B: 1, C: 2, D: 3);
+  (A: 0, B: 1, C: 2, D: 3, E: 4);
+  (A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+);
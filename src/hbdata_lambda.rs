@@ -0,0 +1,58 @@
+use std::io;
+
+use eval::{HBData, HBNodeType, HBEvalResult, HBKeysIter, HBValuesIter, HBIter, SafeWriting};
+
+/// Signature of lambda closures: section source (`None` for `{{lambda}}`),
+/// a callback rendering a template string with the current context, and returns
+/// a template string that will be rendered in place of the expression.
+pub type LambdaFunction = Box<Fn(Option<&str>, &Fn(&str) -> io::Result<String>) -> io::Result<String>>;
+
+/// Callable data node, as mustache lambdas.
+///
+/// Used as `{{name}}`, the lambda is called without section and the returned text
+/// is rendered as a template, then escaped as any value. Used as a section
+/// `{{#name}}…{{/name}}`, the lambda is called with the unrendered block source, and
+/// the returned text is rendered as a template. Inverted sections on a lambda never
+/// render.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use rumblebars::{Template, Lambda};
+///
+/// let mut data = BTreeMap::new();
+/// data.insert("bold".to_string(), Lambda::new(|section, render| {
+///   let content = try!(render(section.unwrap_or("")));
+///   Ok(format!("<b>{}</b>", content))
+/// }));
+///
+/// let template = Template::new("{{#bold}}hello{{/bold}}").unwrap();
+/// assert_eq!(template.eval_to_string(&data).unwrap(), "<b>hello</b>");
+/// ```
+pub struct Lambda {
+  function: LambdaFunction,
+}
+
+impl Lambda {
+  pub fn new<F>(function: F) -> Lambda where F: Fn(Option<&str>, &Fn(&str) -> io::Result<String>) -> io::Result<String> + 'static {
+    Lambda { function: Box::new(function) }
+  }
+
+  /// calls the lambda, `render` being the template rendering callback
+  pub fn call(&self, section: Option<&str>, render: &Fn(&str) -> io::Result<String>) -> io::Result<String> {
+    (self.function)(section, render)
+  }
+}
+
+impl HBData for Lambda {
+  // lambdas are rendered by evaluation, as they need the template context
+  fn write_value(&self, _: &mut SafeWriting) -> HBEvalResult { Ok(()) }
+  fn typed_node(&self) -> HBNodeType<&HBData> { HBNodeType::Leaf(self as &HBData) }
+  fn as_bool(&self) -> bool { true }
+  fn get_key(&self, _: &str) -> Option<&HBData> { None }
+  fn keys<'a>(&'a self) -> HBKeysIter<'a> { Box::new(None.into_iter()) }
+  fn values<'a>(&'a self) -> HBValuesIter<'a> { Box::new(None.into_iter()) }
+  fn iter<'a>(&'a self) -> HBIter<'a> { Box::new(None.into_iter()) }
+  fn as_lambda(&self) -> Option<&Lambda> { Some(self) }
+}
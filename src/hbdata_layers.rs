@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use eval::{HBData, HBNodeType, HBEvalResult, HBKeysIter, HBValuesIter, HBIter, SafeWriting};
+use hbdata_lambda::Lambda;
+
+/// Stacks several data sources into one data node.
+///
+/// Key lookups go through each layer in order, the first layer holding the key wins.
+/// Iteration merges keys of all layers, without duplicates. The node itself (value,
+/// truthiness, type) is the one of the first layer.
+///
+/// Evaluation uses it to make partial hash arguments (`{{> partial key=value}}`)
+/// available on top of the partial context.
+///
+/// # Examples
+///
+/// ```
+/// extern crate rustc_serialize as serialize;
+/// extern crate rumblebars;
+/// # fn main() {
+/// use serialize::json::Json;
+/// use rumblebars::{Template, Layers};
+///
+/// let site = Json::from_str(r##"{"title": "My site", "lang": "en"}"##).unwrap();
+/// let page = Json::from_str(r##"{"title": "About"}"##).unwrap();
+///
+/// let data = Layers::new().layer(&page).layer(&site);
+/// let template = Template::new("{{title}} ({{lang}})").unwrap();
+///
+/// assert_eq!(template.eval_to_string(&data).unwrap(), "About (en)");
+/// # }
+/// ```
+pub struct Layers<'a> {
+  layers: Vec<Layer<'a>>,
+}
+
+enum Layer<'a> {
+  Borrowed(&'a (HBData + 'a)),
+  Owned(Box<HBData + 'a>),
+}
+
+impl<'a> Layer<'a> {
+  fn data(&self) -> &HBData {
+    match *self {
+      Layer::Borrowed(data) => data,
+      Layer::Owned(ref data) => &**data,
+    }
+  }
+}
+
+impl<'a> Layers<'a> {
+  pub fn new() -> Layers<'a> {
+    Layers { layers: Vec::new() }
+  }
+
+  /// builds layers from a list, first element having precedence
+  pub fn with_layers(layers: Vec<&'a (HBData + 'a)>) -> Layers<'a> {
+    Layers { layers: layers.into_iter().map(Layer::Borrowed).collect() }
+  }
+
+  /// adds a layer, used when previous layers do not hold a key
+  pub fn layer(mut self, data: &'a (HBData + 'a)) -> Layers<'a> {
+    self.push(data);
+    self
+  }
+
+  /// same as `layer()` for an existing binding
+  pub fn push(&mut self, data: &'a (HBData + 'a)) {
+    self.layers.push(Layer::Borrowed(data));
+  }
+
+  /// adds a layer owned by the layers, as data computed for them
+  pub fn owned_layer<T: HBData + 'a>(mut self, data: T) -> Layers<'a> {
+    self.layers.push(Layer::Owned(Box::new(data)));
+    self
+  }
+
+  fn first(&self) -> Option<&HBData> {
+    self.layers.first().map(Layer::data)
+  }
+}
+
+impl<'a> HBData for Layers<'a> {
+  fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult {
+    match self.first() {
+      Some(data) => data.write_value(out),
+      None => Ok(()),
+    }
+  }
+
+  fn typed_node(&self) -> HBNodeType<&HBData> {
+    match self.first().map(|data| data.typed_node()) {
+      Some(HBNodeType::Branch(_)) => HBNodeType::Branch(self as &HBData),
+      Some(HBNodeType::Array(_))  => HBNodeType::Array(self as &HBData),
+      Some(HBNodeType::Leaf(_))   => HBNodeType::Leaf(self as &HBData),
+      Some(HBNodeType::Null) | None => HBNodeType::Null,
+    }
+  }
+
+  fn get_key(&self, key: &str) -> Option<&HBData> {
+    self.layers.iter().filter_map(|layer| layer.data().get_key(key)).next()
+  }
+
+  fn as_bool(&self) -> bool {
+    self.first().map(|data| data.as_bool()).unwrap_or(false)
+  }
+
+  fn keys<'b>(&'b self) -> HBKeysIter<'b> {
+    let mut seen = HashSet::new();
+    Box::new(self.layers.iter().flat_map(|layer| layer.data().keys()).filter(move |k| seen.insert(*k)))
+  }
+
+  fn values<'b>(&'b self) -> HBValuesIter<'b> {
+    // collections are not merged, values of an array are the ones of the first layer
+    match self.first().map(|data| (data, data.typed_node())) {
+      Some((data, HBNodeType::Array(_))) => data.values(),
+      _ => Box::new(self.iter().map(|(_, v)| v)),
+    }
+  }
+
+  fn iter<'b>(&'b self) -> HBIter<'b> {
+    Box::new(self.keys().filter_map(move |k| self.get_key(k).map(|v| (k, v))))
+  }
+
+  fn as_lambda(&self) -> Option<&Lambda> {
+    self.first().and_then(|data| data.as_lambda())
+  }
+}
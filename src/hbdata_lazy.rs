@@ -0,0 +1,142 @@
+use std::cell::{Cell, UnsafeCell};
+use std::collections::BTreeMap;
+
+use eval::{HBData, HBNodeType, HBEvalResult, HBKeysIter, HBValuesIter, HBIter, SafeWriting};
+use hbdata_lambda::Lambda;
+
+/// Data node which value is computed by a closure on first access, then cached.
+///
+/// Evaluation only borrows data, so the computed value lives as long as the
+/// `LazyData` itself, and expensive values (aggregates, rendered markdown…) are only
+/// computed when a template actually uses them.
+///
+/// # Examples
+///
+/// ```
+/// use rumblebars::{Template, LazyData};
+///
+/// let lazy = LazyData::new(|| "computed".to_string());
+/// let template = Template::new("{{.}}").unwrap();
+///
+/// assert!(!lazy.is_computed());
+/// assert_eq!(template.eval_to_string(&lazy).unwrap(), "computed");
+/// assert!(lazy.is_computed());
+/// ```
+pub struct LazyData<T> {
+  compute: Box<Fn() -> T>,
+  value: UnsafeCell<Option<T>>,
+  computing: Cell<bool>,
+}
+
+impl<T: HBData> LazyData<T> {
+  pub fn new<F>(compute: F) -> LazyData<T> where F: Fn() -> T + 'static {
+    LazyData {
+      compute: Box::new(compute),
+      value: UnsafeCell::new(None),
+      computing: Cell::new(false),
+    }
+  }
+
+  /// true once the value has been computed
+  pub fn is_computed(&self) -> bool {
+    unsafe { (*self.value.get()).is_some() }
+  }
+
+  /// computed value, calls the closure if not computed yet
+  pub fn get(&self) -> &T {
+    // the value is only written once, while there is no reference to it,
+    // so references handed out are never invalidated
+    unsafe {
+      if (*self.value.get()).is_none() {
+        if self.computing.get() {
+          panic!("LazyData value depends on itself");
+        }
+
+        // reset even when the closure panics, so that `get()` can be retried
+        let _guard = Computing::start(&self.computing);
+        let value = (self.compute)();
+
+        *self.value.get() = Some(value);
+      }
+
+      (*self.value.get()).as_ref().unwrap()
+    }
+  }
+}
+
+// flags a value being computed until dropped
+struct Computing<'a>(&'a Cell<bool>);
+
+impl<'a> Computing<'a> {
+  fn start(computing: &'a Cell<bool>) -> Computing<'a> {
+    computing.set(true);
+    Computing(computing)
+  }
+}
+
+impl<'a> Drop for Computing<'a> {
+  fn drop(&mut self) {
+    self.0.set(false);
+  }
+}
+
+impl<T: HBData> HBData for LazyData<T> {
+  fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult { self.get().write_value(out) }
+  fn typed_node(&self) -> HBNodeType<&HBData> { self.get().typed_node() }
+  fn as_bool(&self) -> bool { self.get().as_bool() }
+  fn get_key(&self, key: &str) -> Option<&HBData> { self.get().get_key(key) }
+  fn keys<'a>(&'a self) -> HBKeysIter<'a> { self.get().keys() }
+  fn values<'a>(&'a self) -> HBValuesIter<'a> { self.get().values() }
+  fn iter<'a>(&'a self) -> HBIter<'a> { self.get().iter() }
+  fn as_lambda(&self) -> Option<&Lambda> { self.get().as_lambda() }
+}
+
+/// Key/value data node that mixes eager values and lazily computed ones.
+///
+/// # Examples
+///
+/// ```
+/// use rumblebars::{Template, LazyMap};
+///
+/// let mut data = LazyMap::new();
+/// data.insert("title".to_string(), "Stats");
+/// data.insert_lazy("total".to_string(), || 6 * 7);
+///
+/// let template = Template::new("{{title}}: {{total}}").unwrap();
+/// assert_eq!(template.eval_to_string(&data).unwrap(), "Stats: 42");
+/// ```
+pub struct LazyMap {
+  entries: BTreeMap<String, Box<HBData>>,
+}
+
+impl LazyMap {
+  pub fn new() -> LazyMap {
+    LazyMap { entries: BTreeMap::new() }
+  }
+
+  /// adds an already computed value
+  pub fn insert<T: HBData + 'static>(&mut self, key: String, value: T) {
+    self.entries.insert(key, Box::new(value));
+  }
+
+  /// adds a value computed on first access
+  pub fn insert_lazy<T, F>(&mut self, key: String, compute: F) where T: HBData + 'static, F: Fn() -> T + 'static {
+    self.entries.insert(key, Box::new(LazyData::new(compute)));
+  }
+}
+
+impl ::std::default::Default for LazyMap {
+  fn default() -> LazyMap {
+    LazyMap::new()
+  }
+}
+
+impl HBData for LazyMap {
+  fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult { self.entries.write_value(out) }
+  fn typed_node(&self) -> HBNodeType<&HBData> { HBNodeType::Branch(self as &HBData) }
+  fn as_bool(&self) -> bool { true }
+  fn get_key(&self, key: &str) -> Option<&HBData> { HBData::get_key(&self.entries, key) }
+  fn keys<'a>(&'a self) -> HBKeysIter<'a> { HBData::keys(&self.entries) }
+  fn values<'a>(&'a self) -> HBValuesIter<'a> { HBData::values(&self.entries) }
+  fn iter<'a>(&'a self) -> HBIter<'a> { HBData::iter(&self.entries) }
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+
+use eval::{HBData, HBNodeType, HBEvalResult, HBKeysIter, HBValuesIter, HBIter, SafeWriting};
+
+/// String that is already safe for the output, written without escaping even
+/// through `{{expression}}`.
+///
+/// Use it for trusted fragments built by your code, or return it from helpers by
+/// writing it to their output.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use rumblebars::{Template, SafeString};
+///
+/// let mut data = BTreeMap::new();
+/// data.insert("trusted".to_string(), SafeString::new("<b>bold</b>"));
+///
+/// let template = Template::new("{{trusted}}").unwrap();
+/// assert_eq!(template.eval_to_string(&data).unwrap(), "<b>bold</b>");
+/// ```
+///
+/// From a helper :
+///
+/// ```
+/// use rumblebars::{Template, EvalContext, HBData, SafeString};
+///
+/// let mut context = EvalContext::new();
+/// context.register_helper("br".to_string(), Box::new(|_, _, out, _| {
+///   SafeString::new("<br>").write_value(out)
+/// }));
+///
+/// let mut buf = Vec::new();
+/// Template::new("{{br}}").unwrap().eval(&"", &mut buf, &context).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(), "<br>");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SafeString(String);
+
+impl SafeString {
+  pub fn new<S: Into<String>>(s: S) -> SafeString {
+    SafeString(s.into())
+  }
+
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+
+  pub fn into_string(self) -> String {
+    self.0
+  }
+}
+
+impl From<String> for SafeString {
+  fn from(s: String) -> SafeString {
+    SafeString(s)
+  }
+}
+
+impl HBData for SafeString {
+  fn write_value(&self, out: &mut SafeWriting) -> HBEvalResult {
+    out.into_unsafe().write_all(self.0.as_bytes())
+  }
+
+  fn typed_node(&self) -> HBNodeType<&HBData> { HBNodeType::Leaf(self as &HBData) }
+  fn as_bool(&self) -> bool { !self.0.is_empty() }
+  fn get_key(&self, _: &str) -> Option<&HBData> { None }
+  fn keys<'a>(&'a self) -> HBKeysIter<'a> { Box::new(None.into_iter()) }
+  fn values<'a>(&'a self) -> HBValuesIter<'a> { Box::new(None.into_iter()) }
+  fn iter<'a>(&'a self) -> HBIter<'a> { Box::new(None.into_iter()) }
+}
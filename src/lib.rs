@@ -7,14 +7,18 @@
 //! to use with rust nighly feature build with ```cargo build --features nightly --no-default-features```
 
 //!
-//! Rumblebars passes **all mustaches specs** [[1]](#1) and **272 handlebars tests** [[2]](#2). Template evaluation is rendered to a `io::Writer`, so that you can choose wether if you hold result in memory or not. It also input data angostic, given that your data structure implements the `HBData` trait (Json implementation provided).
+//! Rumblebars passes **all mustaches specs** [[1]](#1) and **272 handlebars tests** [[2]](#2). Template evaluation is rendered to a `io::Writer` or a `fmt::Write`, so that you can choose wether if you hold result in memory or not. It also input data angostic, given that your data structure implements the `HBData` trait (Json implementation provided, as well as standard library scalars, `Option`, `Vec`, slices, string keyed maps, smart pointers and tuples).
 //!
-//!  [1] <a name="1"></a> except delimiter changes test suite and one test failing because of a trailing space
+//!  [1] <a name="1"></a> except delimiter changes test suite and one test failing because of a trailing space, optional lambdas specs are supported too
 //!  [2] <a name="2"></a> all tests that does not involves javascript in data and partials, and see the [comments for other cases](https://github.com/nicolas-cherel/rumblebars/blob/master/tests/eval/handlebars.rs#L88-L134)
 //!
 //! ## HMTL escaping safety
 //!
-//! All output is filtered by being written to the `SafeWriting` trait. Helpers, just as regular evaluation do for unescaped content, have to opt out escaped writing by calling `SafeWriting::into_unsafe()` that will return the underlying unfiltered writer.
+//! All output is filtered by being written to the `SafeWriting` trait. Helpers, just as regular evaluation do for unescaped content, have to opt out escaped writing by calling `SafeWriting::into_unsafe()` that will return the underlying unfiltered writer. Data opts out by being a `SafeString`.
+//!
+//! Escaping defaults to html, other output formats use another `Escaper` (json strings, javascript, url, csv, LaTeX, markdown, none or your own implementation) set with `EvalContext::set_escaper()`.
+//!
+//! Templates parsed with `parse_contextual()` escape each expression for its position in the html (element content, attribute, url, script or style), see its documentation.
 //!
 //! ## Quick start
 //!
@@ -69,6 +73,16 @@
 //! # }
 //! ```
 //!
+//! Templates also render into any `fmt::Write` with `eval_fmt()`, and are formatted along with their data by `Template::display()` :
+//!
+//! ```
+//! use rumblebars::Template;
+//!
+//! let template = Template::new("{{.}} items").unwrap();
+//! let line = format!("[{}]", template.display(&3));
+//! assert_eq!(&line, "[3 items]");
+//! ```
+//!
 //! ## helpers
 //!
 //! Helpers are registered to the evaluation context. They are boxed closures (you can hold bare function in them too) that have to write their content on the `out: &mut Writer`. If you need to processed content before rendering it to the final `Writer`, just render it to a buffer put into a safe writter.
@@ -96,6 +110,53 @@
 //! # else { panic!("should not reach") }
 //! ```
 //!
+//! ## named templates and partials on disk
+//!
+//! Pages and partials share one namespace in the `EvalContext`, `EvalContext::render()` renders a registered template by name.
+//!
+//! `TemplateLoader` registers all templates of a directory tree as partials, named from their relative path (`shared/header` for `shared/header.hbs`). Parse errors of all files are reported at once, with file names and positions.
+//!
+//! For a fixed set of templates, `Precompiler` generates rust code rendering them from a build script, so that templates are checked at build time and not parsed at runtime.
+//! Single templates can be checked the same way with the `template!` macro of `rumblebars_macros`, expanding to a `Template` built without parsing.
+//! With `typed_template!`, templates are bound to the type of their data as a `TypedTemplate`, and their paths are checked against the fields of derived structs.
+//!
+//! With the `serialization` feature, parsed templates can be cached with `encode_templates()` and loaded back with `decode_templates()`, skipping parsing at startup.
+//!
+//! In development, `FileRegistry` reparses the templates whose files changed before each render, keeping the previous version of templates that fail to parse.
+//!
+//! ## source tools
+//!
+//! `to_source()` prints a syntax tree back to handlebars source. `Formatter` normalizes templates without changing what they render, it is also available from the command line as `hbs fmt`.
+//!
+//! `Visitor` walks syntax trees and `Fold` rewrites them, without matching every nested block by hand.
+//!
+//! `Template::analyze()` lists the data paths, helpers and partials a template references, following registered partials.
+//!
+//! `Linter` reports unknown helpers, missing partials, unescaped expressions and the like, it runs from the command line as `hbs lint`.
+//!
+//! `PartialGraph` is the include graph of registered partials, it finds include cycles and missing partials, and exports the graph to Graphviz (`hbs graph`).
+//!
+//! `Template::check_data()` walks every branch of a template over a json document, and reports the paths it misses, with the position of the expressions reading them, and the data that is never read.
+//!
+
+//! ## custom data
+//!
+//! Any type can be rendered once it implements `HBData`. For plain structs and enums,
+//! the `rumblebars_macros` crate derives it for you :
+//!
+//! ```ignore
+//! #[macro_use] extern crate rumblebars_macros;
+//!
+//! #[derive(HBData)]
+//! struct Page {
+//!   title: String,
+//!   #[hbdata(rename = "author-name")]
+//!   author: String,
+//!   #[hbdata(skip)]
+//!   draft_notes: String,
+//! }
+//! ```
+//!
 
 #![crate_name="rumblebars"]
 
@@ -113,6 +174,8 @@
 
 extern crate regex;
 extern crate rustc_serialize as serialize;
+// path used by the serialization derives
+#[cfg(feature = "serialization")] extern crate rustc_serialize;
 
 
 #[cfg(feature = "nightly")] extern crate test;
@@ -121,9 +184,21 @@ extern crate rustc_serialize as serialize;
 extern crate lazy_static;
 
 pub use self::parse::parse;
+pub use self::parse::parse_detailed;
+pub use self::parse::ParseFailure;
 pub use self::parse::ParseError;
 pub use self::parse::Template;
+pub use self::parse::TemplateDisplay;
+pub use self::parse::Entries;
+pub use self::parse::HBEntry;
+pub use self::parse::HBExpression;
+pub use self::parse::HBComment;
+pub use self::parse::HBValHolder;
+pub use self::parse::RenderOptions;
+pub use self::parse::Position;
+pub use self::autoescape::parse_contextual;
 pub use self::eval::eval;
+pub use self::eval::eval_fmt;
 pub use self::eval::HBData;
 pub use self::eval::HBIter;
 pub use self::eval::HBKeysIter;
@@ -134,17 +209,78 @@ pub use self::eval::HelperOptions;
 pub use self::eval::HelperOptionsByName;
 pub use self::eval::SafeWriting;
 pub use self::eval::HTMLSafeWriter;
+pub use self::eval::FmtWriter;
+pub use self::hbdata_lazy::LazyData;
+pub use self::hbdata_lazy::LazyMap;
+pub use self::hbdata_layers::Layers;
+pub use self::hbdata_lambda::Lambda;
+pub use self::hbdata_lambda::LambdaFunction;
+pub use self::hbdata_safe::SafeString;
+pub use self::escape::Escaper;
+pub use self::escape::EscapeWriter;
+pub use self::escape::HTMLEscaper;
+pub use self::escape::JSONStringEscaper;
+pub use self::escape::JavascriptEscaper;
+pub use self::escape::URLEscaper;
+pub use self::escape::CSVEscaper;
+pub use self::escape::LatexEscaper;
+pub use self::escape::MarkdownEscaper;
+pub use self::escape::NoEscaper;
+pub use self::loader::TemplateLoader;
+pub use self::loader::FileRegistry;
+pub use self::codegen::Precompiler;
+pub use self::codegen::precompile;
+pub use self::codegen::template_code;
+pub use self::codegen::PrecompileError;
+pub use self::typed::TypedTemplate;
+pub use self::typed::HBItems;
+pub use self::typed::{HBField, KeyChar, UncheckedData};
+pub use self::printer::to_source;
+pub use self::format::Formatter;
+pub use self::format::FormatError;
+pub use self::visit::{Visitor, walk_entries, walk_entry, walk_expression, walk_value};
+pub use self::visit::{Fold, noop_fold_entries, noop_fold_entry, noop_fold_expression, noop_fold_value};
+pub use self::analysis::{Analysis, PathUsage, analyze};
+pub use self::lint::{Linter, Lint, LintRule, Severity};
+pub use self::graph::{PartialGraph, Cycle};
+pub use self::shape::{DataReport, MissingPath, check_data};
+#[cfg(feature = "serialization")] pub use self::cache::encode_templates;
+#[cfg(feature = "serialization")] pub use self::cache::decode_templates;
+#[cfg(feature = "serialization")] pub use self::cache::CacheError;
+#[cfg(feature = "serialization")] pub use self::cache::CACHE_VERSION;
+pub use self::loader::LoadError;
+pub use self::loader::LoadErrorKind;
 
 mod eval;
 mod helpers_builtins;
+mod hbdata_impls;
+mod hbdata_lazy;
+mod hbdata_layers;
+mod hbdata_lambda;
+mod hbdata_safe;
+mod escape;
+mod autoescape;
+mod loader;
+mod codegen;
+mod typed;
+mod printer;
+mod format;
+mod visit;
+mod analysis;
+mod lint;
+mod graph;
+mod shape;
+#[cfg(feature = "serialization")] mod cache;
+pub mod compiled;
 
 pub mod preludes {
   pub mod hbdata {
-    pub use super::super::eval::{HBData, HBIter, HBKeysIter, HBValuesIter, HBEvalResult, HBNodeType, SafeWriting};
+    pub use super::super::eval::{HBData, HBIter, HBKeysIter, HBValuesIter, HBEvalResult, HBNodeType, SafeWriting, write_values_list};
   }
 
   pub mod helpers {
     pub use super::super::eval::{EvalContext, HelperOptions, HelperOptionsByName, SafeWriting, HTMLSafeWriter};
+    pub use super::super::escape::{Escaper, EscapeWriter};
   }
 }
 
@@ -0,0 +1,358 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+
+use regex::Regex;
+use serialize::json::{Json, ToJson};
+
+use parse::{Entries, HBEntry, HBExpression, HBComment, HBValHolder, Position, ParseFailure, parse_detailed};
+use eval::EvalContext;
+use loader::{LoadError, LoadErrorKind};
+use printer::path_source;
+use visit::{Visitor, walk_expression};
+
+lazy_static! {
+  static ref LINT_DISABLE: Regex = Regex::new(r"(?s)^\{\{~?!(?:--)?\s*lint-disable\b(.*?)(?:--)?~?\}\}$").unwrap();
+}
+
+/// Checks run by the `Linter`, named in kebab case in output and in
+/// `{{!-- lint-disable rule --}}` comments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintRule {
+  /// helper call, with parameters or options, to an unregistered helper
+  UnknownHelper,
+  /// partial that is not registered
+  MissingPartial,
+  /// registered partial that no page includes
+  UnusedPartial,
+  /// `{{{ }}}` or `{{& }}` expression, that is not html escaped
+  TripleStash,
+  /// path going up more parent contexts than allowed, as `../../../name`
+  DeepPath,
+  /// block without content
+  EmptyBlock,
+  /// option given twice, as `{{link url class="a" class="b"}}`
+  DuplicateHashKey,
+  /// `lint-disable` comment naming a rule that does not exist
+  UnknownRule,
+}
+
+const RULES: [LintRule; 8] = [
+  LintRule::UnknownHelper,
+  LintRule::MissingPartial,
+  LintRule::UnusedPartial,
+  LintRule::TripleStash,
+  LintRule::DeepPath,
+  LintRule::EmptyBlock,
+  LintRule::DuplicateHashKey,
+  LintRule::UnknownRule,
+];
+
+impl LintRule {
+  pub fn name(&self) -> &'static str {
+    match *self {
+      LintRule::UnknownHelper => "unknown-helper",
+      LintRule::MissingPartial => "missing-partial",
+      LintRule::UnusedPartial => "unused-partial",
+      LintRule::TripleStash => "triple-stash",
+      LintRule::DeepPath => "deep-path",
+      LintRule::EmptyBlock => "empty-block",
+      LintRule::DuplicateHashKey => "duplicate-hash-key",
+      LintRule::UnknownRule => "unknown-rule",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<LintRule> {
+    RULES.iter().find(|rule| rule.name() == name).cloned()
+  }
+
+  /// severity used unless configured otherwise
+  pub fn default_severity(&self) -> Severity {
+    match *self {
+      LintRule::UnknownHelper | LintRule::MissingPartial | LintRule::DuplicateHashKey => Severity::Error,
+      _ => Severity::Warning,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+  /// the rule is not checked
+  Allow,
+  Warning,
+  Error,
+}
+
+impl Severity {
+  pub fn name(&self) -> &'static str {
+    match *self {
+      Severity::Allow => "allow",
+      Severity::Warning => "warning",
+      Severity::Error => "error",
+    }
+  }
+}
+
+/// Problem found by the `Linter`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+  /// name of the template it is found in
+  pub template: String,
+  /// 1-based line of the tag, 0 for lints on a whole template
+  pub line: usize,
+  /// 1-based column of the tag, in characters
+  pub column: usize,
+  pub rule: LintRule,
+  pub severity: Severity,
+  pub message: String,
+}
+
+/// `template:line:column: severity[rule]: message`, without position for lints
+/// on a whole template
+impl fmt::Display for Lint {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.line > 0 {
+      try!(write!(f, "{}:{}:{}", self.template, self.line, self.column));
+    } else {
+      try!(write!(f, "{}", self.template));
+    }
+    write!(f, ": {}[{}]: {}", self.severity.name(), self.rule.name(), self.message)
+  }
+}
+
+/// `{"template": .., "line": .., "column": .., "rule": .., "severity": .., "message": ..}`
+impl ToJson for Lint {
+  fn to_json(&self) -> Json {
+    let mut object = BTreeMap::new();
+    object.insert("template".to_string(), self.template.to_json());
+    object.insert("line".to_string(), self.line.to_json());
+    object.insert("column".to_string(), self.column.to_json());
+    object.insert("rule".to_string(), self.rule.name().to_json());
+    object.insert("severity".to_string(), self.severity.name().to_json());
+    object.insert("message".to_string(), self.message.to_json());
+    Json::Object(object)
+  }
+}
+
+/// Lint pass over template sources, checked against the helpers and
+/// partials of an `EvalContext`.
+///
+/// Rules can be turned off for a template with a comment listing them,
+/// `{{!-- lint-disable triple-stash deep-path --}}`, or all of them when none
+/// is listed. Names that are not rules are reported as `unknown-rule`.
+///
+/// # Examples
+///
+/// ```
+/// use rumblebars::{EvalContext, Linter, LintRule, Severity};
+///
+/// let linter = Linter::new().severity(LintRule::TripleStash, Severity::Error);
+/// let lints = linter.lint("post", "<h1>{{title}}</h1>{{{body}}}{{> footer}}", &EvalContext::new()).unwrap();
+///
+/// assert_eq!(lints[0].to_string(), "post:1:19: error[triple-stash]: `body` is not html escaped");
+/// assert_eq!(lints[1].to_string(), "post:1:29: error[missing-partial]: partial `footer` is not registered");
+///
+/// let allowed = linter.lint("post", "{{!-- lint-disable triple-stash --}}{{{body}}}", &EvalContext::new()).unwrap();
+/// assert!(allowed.is_empty());
+/// ```
+pub struct Linter {
+  severities: HashMap<LintRule, Severity>,
+  max_parent_depth: usize,
+}
+
+impl Default for Linter {
+  fn default() -> Linter {
+    Linter { severities: HashMap::new(), max_parent_depth: 2 }
+  }
+}
+
+impl Linter {
+  /// linter with the default severity of each rule, allowing `../../name`
+  pub fn new() -> Linter {
+    Default::default()
+  }
+
+  /// sets the severity of `rule`, `Severity::Allow` turns it off
+  pub fn severity(mut self, rule: LintRule, severity: Severity) -> Linter {
+    self.severities.insert(rule, severity);
+    self
+  }
+
+  /// parent contexts a path can go up to before `deep-path` is reported
+  pub fn max_parent_depth(mut self, depth: usize) -> Linter {
+    self.max_parent_depth = depth;
+    self
+  }
+
+  fn severity_of(&self, rule: LintRule) -> Severity {
+    self.severities.get(&rule).cloned().unwrap_or(rule.default_severity())
+  }
+
+  /// lints of the template `name`, found in `source` order
+  pub fn lint(&self, name: &str, source: &str, eval_context: &EvalContext) -> Result<Vec<Lint>, ParseFailure> {
+    let template = try!(parse_detailed(source));
+
+    let mut lints = LintVisitor { linter: self, context: eval_context, position: Position::default(), lints: Vec::new(), disabled: Default::default() };
+    lints.visit_entries(&template.entries);
+
+    Ok(self.report(name, &lints.disabled, lints.lints))
+  }
+
+  /// Lints every template of `templates`, given as name and source, the
+  /// ones that do not parse are reported together.
+  pub fn lint_templates(&self, templates: &[(String, String)], eval_context: &EvalContext) -> Result<Vec<Lint>, Vec<LoadError>> {
+    let mut lints = Vec::new();
+    let mut errors = Vec::new();
+
+    for &(ref name, ref source) in templates.iter() {
+      match self.lint(name, source, eval_context) {
+        Ok(template_lints) => lints.extend(template_lints),
+        Err(failure) => errors.push(LoadError { path: PathBuf::from(name), kind: LoadErrorKind::Parse(failure) }),
+      }
+    }
+
+    if errors.is_empty() { Ok(lints) } else { Err(errors) }
+  }
+
+  /// Partials registered in `eval_context` that the registered templates
+  /// `pages` never include, directly or through other partials. Pages share
+  /// the partials namespace and are not reported.
+  ///
+  /// Having no source, these lints are only turned off by severity.
+  pub fn unused_partials(&self, pages: &[&str], eval_context: &EvalContext) -> Vec<Lint> {
+    let mut used: BTreeSet<String> = pages.iter().map(|page| page.to_string()).collect();
+    for page in pages.iter() {
+      if let Some(analysis) = eval_context.analyze(page) {
+        used.extend(analysis.partials);
+      }
+    }
+
+    let registered: BTreeSet<String> = eval_context.partials().keys().cloned().collect();
+    let found = registered.difference(&used).map(|name| {
+      (name.clone(), LintRule::UnusedPartial, format!("partial `{}` is never included", name))
+    }).collect::<Vec<_>>();
+
+    found.into_iter().flat_map(|(name, rule, message)| self.report(&name, &Default::default(), vec![(rule, Position::default(), message)])).collect()
+  }
+
+  // lints with their severity, without the allowed and disabled ones
+  fn report(&self, name: &str, disabled: &Disabled, found: Vec<(LintRule, Position, String)>) -> Vec<Lint> {
+    found.into_iter().filter_map(|(rule, position, message)| {
+      let severity = self.severity_of(rule);
+      if severity == Severity::Allow || disabled.all || disabled.rules.contains(&rule) {
+        None
+      } else {
+        Some(Lint {
+          template: name.to_string(),
+          line: position.line,
+          column: position.column,
+          rule: rule,
+          severity: severity,
+          message: message,
+        })
+      }
+    }).collect()
+  }
+}
+
+// rules turned off by `lint-disable` comments
+#[derive(Default)]
+struct Disabled {
+  all: bool,
+  rules: BTreeSet<LintRule>,
+}
+
+struct LintVisitor<'a> {
+  linter: &'a Linter,
+  context: &'a EvalContext,
+  // position of the tag being visited
+  position: Position,
+  lints: Vec<(LintRule, Position, String)>,
+  disabled: Disabled,
+}
+
+impl<'a> LintVisitor<'a> {
+  fn push(&mut self, rule: LintRule, message: String) {
+    self.lints.push((rule, self.position, message));
+  }
+
+  fn path(&mut self, path: &[String]) {
+    let depth = path.iter().take_while(|key| *key == ".." || *key == ".").filter(|key| *key == "..").count();
+    if depth > self.linter.max_parent_depth {
+      self.push(LintRule::DeepPath, format!("`{}` goes up {} parent contexts", path_source(path), depth));
+    }
+  }
+
+  fn options(&mut self, exp: &HBExpression) {
+    let mut names = BTreeSet::new();
+    for &(ref name, _) in exp.options.iter() {
+      if !names.insert(name) {
+        self.push(LintRule::DuplicateHashKey, format!("option `{}` is given twice to `{}`", name, exp.path()));
+      }
+    }
+  }
+}
+
+fn is_empty(entries: &Entries) -> bool {
+  entries.iter().all(|entry| match **entry {
+    HBEntry::Raw(ref s) => s.trim().is_empty(),
+    HBEntry::Comment(_) => true,
+    _ => false,
+  })
+}
+
+impl<'a> Visitor for LintVisitor<'a> {
+  fn visit_expression(&mut self, exp: &HBExpression) {
+    self.position = exp.position;
+    let helper = exp.base.len() == 1 && self.context.has_helper_with_name(&exp.base[0]);
+    if !helper && (!exp.params.is_empty() || !exp.options.is_empty()) {
+      self.push(LintRule::UnknownHelper, format!("helper `{}` is not registered", exp.path()));
+    }
+    if !helper {
+      self.path(&exp.base);
+    }
+    if !exp.render_options.escape && exp.block.is_none() {
+      self.push(LintRule::TripleStash, format!("`{}` is not html escaped", path_source(&exp.base)));
+    }
+    if exp.block.as_ref().map(|block| is_empty(block)).unwrap_or(false) && exp.else_block.as_ref().map(|block| is_empty(block)).unwrap_or(true) {
+      self.push(LintRule::EmptyBlock, format!("block `{}` is empty", exp.path()));
+    }
+    self.options(exp);
+
+    walk_expression(self, exp);
+  }
+
+  fn visit_partial(&mut self, exp: &HBExpression) {
+    self.position = exp.position;
+    let name = exp.partial_name();
+    if self.context.partial_with_name(&name).is_none() {
+      self.push(LintRule::MissingPartial, format!("partial `{}` is not registered", name));
+    }
+    self.options(exp);
+
+    walk_expression(self, exp);
+  }
+
+  fn visit_comment(&mut self, comment: &HBComment) {
+    let names = match LINT_DISABLE.captures(&comment.source) {
+      Some(captures) => captures.at(1).unwrap_or("").split_whitespace().map(|name| name.to_string()).collect::<Vec<_>>(),
+      None => return,
+    };
+
+    // only a comment without names disables every rule
+    self.position = comment.position;
+    self.disabled.all = self.disabled.all || names.is_empty();
+    for name in names.iter() {
+      match LintRule::from_name(name) {
+        Some(rule) => { self.disabled.rules.insert(rule); },
+        None => self.push(LintRule::UnknownRule, format!("`{}` is not a lint rule", name)),
+      }
+    }
+  }
+
+  fn visit_value(&mut self, value: &HBValHolder) {
+    if let HBValHolder::Path(ref path) = *value {
+      self.path(path);
+    }
+  }
+}
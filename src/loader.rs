@@ -0,0 +1,295 @@
+use std::io;
+use std::io::Read;
+use std::fs;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use std::collections::{HashMap, BTreeMap};
+
+use parse::{Template, ParseFailure, parse_detailed};
+use codegen::PrecompileError;
+use eval::{EvalContext, HBData, HBEvalResult};
+
+/// Loads templates from a directory tree, each one named from its path
+/// relative to the directory, without extension (`shared/header` for
+/// `shared/header.hbs`).
+///
+/// Files without the loader extension are ignored. All errors are reported
+/// together, so that every broken template can be fixed in one pass.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use rumblebars::{EvalContext, TemplateLoader};
+///
+/// let mut context = EvalContext::new();
+///
+/// if let Err(errors) = TemplateLoader::new().extension("html").register_partials(Path::new("templates"), &mut context) {
+///   for error in errors {
+///     println!("{}", error);
+///   }
+/// }
+/// ```
+pub struct TemplateLoader {
+  extension: String,
+}
+
+/// Failure to load a template file
+#[derive(Debug)]
+pub struct LoadError {
+  pub path: PathBuf,
+  pub kind: LoadErrorKind,
+}
+
+#[derive(Debug)]
+pub enum LoadErrorKind {
+  Io(io::Error),
+  Parse(ParseFailure),
+  /// parsed template that `Precompiler` can't compile
+  Precompile(PrecompileError),
+}
+
+impl fmt::Display for LoadError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.kind {
+      LoadErrorKind::Io(ref e) => write!(f, "{}: {}", self.path.display(), e),
+      LoadErrorKind::Parse(ref failure) => write!(f, "{}:{}", self.path.display(), failure),
+      LoadErrorKind::Precompile(ref error) => write!(f, "{}:{}", self.path.display(), error),
+    }
+  }
+}
+
+impl Default for TemplateLoader {
+  fn default() -> TemplateLoader {
+    TemplateLoader { extension: "hbs".to_string() }
+  }
+}
+
+impl TemplateLoader {
+  /// loader of `.hbs` files
+  pub fn new() -> TemplateLoader {
+    Default::default()
+  }
+
+  /// sets the extension of loaded files, without the leading dot
+  pub fn extension(mut self, extension: &str) -> TemplateLoader {
+    self.extension = extension.to_string();
+    self
+  }
+
+  /// named templates of the directory tree, sorted by name
+  pub fn load(&self, dir: &Path) -> Result<Vec<(String, Template)>, Vec<LoadError>> {
+    let mut errors = Vec::new();
+
+    let mut templates = Vec::new();
+    for (name, path, source) in self.read_files(dir, &mut errors) {
+      match parse_detailed(&source) {
+        Ok(template) => templates.push((name, template)),
+        Err(failure) => errors.push(LoadError { path: path, kind: LoadErrorKind::Parse(failure) }),
+      }
+    }
+
+    if errors.is_empty() { Ok(templates) } else { Err(errors) }
+  }
+
+  /// named sources of the directory tree, sorted by name, for tools that need
+  /// more than the syntax tree
+  pub fn load_sources(&self, dir: &Path) -> Result<Vec<(String, String)>, Vec<LoadError>> {
+    let mut errors = Vec::new();
+    let sources = self.read_files(dir, &mut errors).into_iter().map(|(name, _, source)| (name, source)).collect();
+
+    if errors.is_empty() { Ok(sources) } else { Err(errors) }
+  }
+
+  // named sources, with their path, sorted by name
+  fn read_files(&self, dir: &Path, errors: &mut Vec<LoadError>) -> Vec<(String, PathBuf, String)> {
+    let mut files = Vec::new();
+    self.collect_files(dir, &mut files, errors);
+
+    let mut sources = Vec::new();
+    for path in files {
+      let mut source = String::new();
+      if let Err(e) = fs::File::open(&path).and_then(|mut f| f.read_to_string(&mut source)) {
+        errors.push(LoadError { path: path, kind: LoadErrorKind::Io(e) });
+        continue;
+      }
+      sources.push((template_name(dir, &path), path, source));
+    }
+
+    sources.sort_by(|&(ref a, _, _), &(ref b, _, _)| a.cmp(b));
+    sources
+  }
+
+  /// registers the templates of the directory tree as partials, nothing is
+  /// registered when a template fails to load
+  pub fn register_partials(&self, dir: &Path, eval_context: &mut EvalContext) -> Result<(), Vec<LoadError>> {
+    for (name, template) in try!(self.load(dir)) {
+      eval_context.register_partial(name, template);
+    }
+
+    Ok(())
+  }
+
+  fn collect_files(&self, dir: &Path, files: &mut Vec<PathBuf>, errors: &mut Vec<LoadError>) {
+    let entries = match fs::read_dir(dir) {
+      Ok(entries) => entries,
+      Err(e) => return errors.push(LoadError { path: dir.to_path_buf(), kind: LoadErrorKind::Io(e) }),
+    };
+
+    let mut paths = Vec::new();
+    for entry in entries {
+      match entry {
+        Ok(entry) => paths.push(entry.path()),
+        Err(e) => errors.push(LoadError { path: dir.to_path_buf(), kind: LoadErrorKind::Io(e) }),
+      }
+    }
+    paths.sort();
+
+    for path in paths {
+      if path.is_dir() {
+        self.collect_files(&path, files, errors);
+      } else if path.extension().map(|ext| ext == &self.extension[..]).unwrap_or(false) {
+        files.push(path);
+      }
+    }
+  }
+}
+
+/// Templates of a directory tree registered as partials of its `EvalContext`,
+/// reloaded when their files change.
+///
+/// With hot reload, meant for development, each render rescans the directory:
+/// new and modified files are parsed, partials of removed files are dropped.
+/// When a modified file fails to parse, its previous version stays in use and
+/// the error is kept in `errors()` until the file is fixed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use rumblebars::{EvalContext, FileRegistry, TemplateLoader};
+///
+/// let mut registry = FileRegistry::new(Path::new("templates"), TemplateLoader::new(), EvalContext::new()).hot_reload(true);
+/// let mut out = Vec::new();
+///
+/// registry.render("pages/index", &"", &mut out).unwrap();
+///
+/// for error in registry.errors() {
+///   println!("{}", error);
+/// }
+/// ```
+pub struct FileRegistry {
+  dir: PathBuf,
+  loader: TemplateLoader,
+  context: EvalContext,
+  hot_reload: bool,
+  // modification time and length of each file, when last parsed
+  versions: HashMap<PathBuf, (SystemTime, u64)>,
+  parse_errors: BTreeMap<PathBuf, LoadError>,
+  io_errors: Vec<LoadError>,
+}
+
+impl FileRegistry {
+  /// loads the directory templates into `eval_context`, without hot reload
+  pub fn new(dir: &Path, loader: TemplateLoader, eval_context: EvalContext) -> FileRegistry {
+    let mut registry = FileRegistry {
+      dir: dir.to_path_buf(),
+      loader: loader,
+      context: eval_context,
+      hot_reload: false,
+      versions: HashMap::new(),
+      parse_errors: BTreeMap::new(),
+      io_errors: Vec::new(),
+    };
+
+    registry.refresh();
+    registry
+  }
+
+  /// sets if files are checked for changes on each render
+  pub fn hot_reload(mut self, hot_reload: bool) -> FileRegistry {
+    self.hot_reload = hot_reload;
+    self
+  }
+
+  pub fn context(&self) -> &EvalContext {
+    &self.context
+  }
+
+  /// context, to register helpers or other partials
+  pub fn context_mut(&mut self) -> &mut EvalContext {
+    &mut self.context
+  }
+
+  /// errors of the last refresh, including files that still fail to parse
+  pub fn errors(&self) -> Vec<&LoadError> {
+    self.io_errors.iter().chain(self.parse_errors.values()).collect()
+  }
+
+  /// rescans the directory and reparses changed files, returns true when no error remains
+  pub fn refresh(&mut self) -> bool {
+    let mut files = Vec::new();
+    self.io_errors.clear();
+    self.loader.collect_files(&self.dir, &mut files, &mut self.io_errors);
+
+    let removed: Vec<PathBuf> = self.versions.keys().filter(|path| !files.contains(path)).cloned().collect();
+    for path in removed {
+      self.versions.remove(&path);
+      self.context.unregister_partial(&template_name(&self.dir, &path));
+    }
+    self.parse_errors = ::std::mem::replace(&mut self.parse_errors, BTreeMap::new()).into_iter()
+      .filter(|&(ref path, _)| files.contains(path))
+      .collect();
+
+    for path in files {
+      let version = match fs::metadata(&path).and_then(|m| m.modified().map(|t| (t, m.len()))) {
+        Ok(version) => version,
+        Err(e) => { self.io_errors.push(LoadError { path: path, kind: LoadErrorKind::Io(e) }); continue },
+      };
+
+      if self.versions.get(&path) == Some(&version) {
+        continue;
+      }
+
+      self.versions.insert(path.clone(), version);
+      match read_template(&path) {
+        Ok(template) => {
+          self.parse_errors.remove(&path);
+          self.context.register_partial(template_name(&self.dir, &path), template);
+        },
+        Err(kind) => {
+          self.parse_errors.insert(path.clone(), LoadError { path: path, kind: kind });
+        },
+      }
+    }
+
+    self.io_errors.is_empty() && self.parse_errors.is_empty()
+  }
+
+  /// renders the template `name`, after a refresh when hot reloading
+  pub fn render(&mut self, name: &str, data: &HBData, out: &mut io::Write) -> HBEvalResult {
+    if self.hot_reload {
+      self.refresh();
+    }
+
+    self.context.render(name, data, out)
+  }
+}
+
+fn read_template(path: &Path) -> Result<Template, LoadErrorKind> {
+  let mut source = String::new();
+  try!(fs::File::open(path).and_then(|mut f| f.read_to_string(&mut source)).map_err(LoadErrorKind::Io));
+
+  parse_detailed(&source).map_err(LoadErrorKind::Parse)
+}
+
+// relative path without extension, `/` separated on all platforms
+fn template_name(dir: &Path, path: &Path) -> String {
+  let relative = path.strip_prefix(dir).unwrap_or(path).with_extension("");
+
+  relative.components()
+    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+    .collect::<Vec<_>>()
+    .join("/")
+}
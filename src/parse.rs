@@ -1,8 +1,9 @@
 use std::io::BufReader;
 use serialize::json::Json;
+#[cfg(feature = "serialization")] use serialize::{Encodable, Decodable, Encoder, Decoder};
 use regex::Regex;
 
-use self::Token::{TokSimpleExp, TokNoEscapeExp, TokCommentExp, TokBlockExp, TokBlockElseCond, TokBlockEndExp, TokPartialExp, TokRaw};
+use self::Token::{TokSimpleExp, TokNoEscapeExp, TokCommentExp, TokBlockExp, TokBlockElseCond, TokBlockEndExp, TokPartialExp, TokRaw, TokEscaped};
 use self::HBToken::{TokPathEntry,TokNoWhiteSpaceBefore, TokNoWhiteSpaceAfter,TokStringParam,TokParamStart, TokParamSep, TokOption, TokLeadingWhiteSpace, TokTrailingWhiteSpace};
 
 #[derive(Debug)]
@@ -16,6 +17,17 @@ pub enum Token {
   TokBlockElseCond(String),
   TokBlockEndExp(String,),
   TokRaw(String),
+  TokEscaped(String),
+}
+
+impl Token {
+  // template source matched by the token
+  fn source(&self) -> &str {
+    match self {
+      &TokSimpleExp(ref s) | &TokNoEscapeExp(ref s) | &TokCommentExp(ref s) | &TokPartialExp(ref s) |
+      &TokBlockExp(ref s, _) | &TokBlockElseCond(ref s) | &TokBlockEndExp(ref s) | &TokRaw(ref s) | &TokEscaped(ref s) => s,
+    }
+  }
 }
 
 #[derive(Debug)]
@@ -71,8 +83,8 @@ rustlex! HandleBarsLexer {
   COMMENT_EXP       => |lexer:&mut HandleBarsLexer<R>| { Some( TokCommentExp(    lexer.yystr() ) ) }
 
   PASS_ESC          => |lexer:&mut HandleBarsLexer<R>| { Some( TokRaw( lexer.yystr()    ) ) }
-  ESCAPED_EXP       => |    _:&mut HandleBarsLexer<R>| { Some( TokRaw( "{".to_string()  ) ) }
-  ESCAPED_ESC       => |    _:&mut HandleBarsLexer<R>| { Some( TokRaw( "\\".to_string() ) ) }
+  ESCAPED_EXP       => |lexer:&mut HandleBarsLexer<R>| { Some( TokEscaped( lexer.yystr() ) ) }
+  ESCAPED_ESC       => |lexer:&mut HandleBarsLexer<R>| { Some( TokEscaped( lexer.yystr() ) ) }
   ESCAPED_SKIP      => |lexer:&mut HandleBarsLexer<R>| { Some( TokRaw( lexer.yystr()    ) ) }
 }
 
@@ -235,16 +247,51 @@ pub enum HBValHolder {
   Literal(Json, String),
 }
 
+// literals are stored as their source, the json value is parsed back
+#[cfg(feature = "serialization")]
+impl Encodable for HBValHolder {
+  fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+    s.emit_enum("HBValHolder", |s| match *self {
+      HBValHolder::String(ref v) => s.emit_enum_variant("String", 0, 1, |s| s.emit_enum_variant_arg(0, |s| v.encode(s))),
+      HBValHolder::Path(ref p) => s.emit_enum_variant("Path", 1, 1, |s| s.emit_enum_variant_arg(0, |s| p.encode(s))),
+      HBValHolder::Literal(_, ref source) => s.emit_enum_variant("Literal", 2, 1, |s| s.emit_enum_variant_arg(0, |s| source.encode(s))),
+    })
+  }
+}
+
+#[cfg(feature = "serialization")]
+impl Decodable for HBValHolder {
+  fn decode<D: Decoder>(d: &mut D) -> Result<HBValHolder, D::Error> {
+    d.read_enum("HBValHolder", |d| d.read_enum_variant(&["String", "Path", "Literal"], |d, variant| {
+      match variant {
+        0 => d.read_enum_variant_arg(0, Decodable::decode).map(HBValHolder::String),
+        1 => d.read_enum_variant_arg(0, Decodable::decode).map(HBValHolder::Path),
+        _ => {
+          let source: String = try!(d.read_enum_variant_arg(0, Decodable::decode));
+          match Json::from_str(&source) {
+            Ok(json) => Ok(HBValHolder::Literal(json, source)),
+            Err(_) => Err(d.error(&format!("invalid literal ‘{}’", source))),
+          }
+        },
+      }
+    }))
+  }
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
 pub struct RenderOptions {
   pub escape: bool,
   pub inverse: bool,
   pub indent: Option<String>,
   pub no_leading_whitespace: bool,
   pub no_trailing_whitespace: bool,
+  /// html context for contextual autoescaping
+  pub context: Option<HTMLContext>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
 pub struct HBExpression {
   pub base: Vec<String>,
   pub params: Vec<HBValHolder>,
@@ -252,9 +299,55 @@ pub struct HBExpression {
   pub render_options: RenderOptions,
   pub block: Option<Box<Entries>>,
   pub else_block: Option<Box<Entries>>,
+  /// unparsed block content, as given to lambdas
+  pub block_source: Option<String>,
+  /// where the opening tag starts in the template source
+  pub position: Position,
+}
+
+/// 1-based line and column, in characters, of a tag in its template source.
+/// Line is 0 for trees that are not parsed from a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
+pub struct Position {
+  pub line: usize,
+  pub column: usize,
+}
+
+// positions of increasing offsets in a template, lines are counted once
+struct Positions<'a> {
+  template: &'a str,
+  offset: usize,
+  line: usize,
+  line_start: usize,
+}
+
+impl<'a> Positions<'a> {
+  fn new(template: &'a str) -> Positions<'a> {
+    Positions { template: template, offset: 0, line: 1, line_start: 0 }
+  }
+
+  fn at(&mut self, offset: usize) -> Position {
+    let template = self.template;
+    let counted = &template[self.offset..offset];
+
+    self.line += counted.matches('\n').count();
+    if let Some(i) = counted.rfind('\n') {
+      self.line_start = self.offset + i + 1;
+    }
+    self.offset = offset;
+
+    Position { line: self.line, column: template[self.line_start..offset].chars().count() + 1 }
+  }
 }
 
 impl HBExpression {
+  /// template name of a partial as written, `a.b` and `a/b` being different
+  /// templates. Names are kept as a single key of `base` on parsing.
+  pub fn partial_name(&self) -> String {
+    self.base.join("/")
+  }
+
   pub fn path(&self) -> String {
     let mut r = String::new();
     self.base.iter().take(self.base.len() - 1).fold(&mut r, |mut a, i| {a.push_str(&i); a.push('.'); a});
@@ -263,13 +356,24 @@ impl HBExpression {
   }
 }
 
+/// `{{! comment }}` tag as written, kept for printing, it renders nothing
+#[derive(Debug)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
+pub struct HBComment {
+  pub source: String,
+  pub render_options: RenderOptions,
+  pub position: Position,
+}
+
 type HBExpressionParsing = (Option<String>, HBExpression, Option<String>);
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
 pub enum HBEntry {
   Raw(String),
   Eval(HBExpression),
   Partial(HBExpression),
+  Comment(HBComment),
 }
 
 impl HBEntry {
@@ -287,7 +391,9 @@ pub type ParseResult = Result<Template, (ParseError, Option<String>)>;
 
 
 use std::io;
-use super::{HBData, HBEvalResult, EvalContext, eval};
+use std::fmt;
+use super::{HBData, HBEvalResult, EvalContext, eval, eval_fmt, to_source, analyze, Analysis, check_data, DataReport};
+use autoescape::HTMLContext;
 
 
 ///
@@ -300,6 +406,7 @@ use super::{HBData, HBEvalResult, EvalContext, eval};
 ///
 /// (see crate docs)
 
+#[cfg_attr(feature = "serialization", derive(RustcEncodable, RustcDecodable))]
 pub struct Template {
   pub entries: Entries
 }
@@ -309,14 +416,73 @@ impl Template {
     parse(template)
   }
 
-  pub fn eval_to_string(&self, data: &HBData) -> Option<String> {
-    let mut buf = Vec::new();
-    self.eval(data, &mut buf, &Default::default()).ok().and_then(|_| String::from_utf8(buf).ok())
+  /// handlebars source of the template, see `to_source()`
+  pub fn to_source(&self) -> String {
+    to_source(&self.entries)
+  }
+
+  /// data paths, helpers and partials the template references, see `analyze()`
+  pub fn analyze(&self, eval_context: &EvalContext) -> Analysis {
+    analyze(&self.entries, eval_context)
+  }
+
+  /// paths of the template missing from `data`, and data it never reads, see `check_data()`
+  pub fn check_data(&self, data: &Json, eval_context: &EvalContext) -> DataReport {
+    check_data(self, data, eval_context)
+  }
+
+  /// renders with a default context, the error is the evaluation one
+  pub fn eval_to_string(&self, data: &HBData) -> io::Result<String> {
+    let mut s = String::new();
+    try!(self.eval_fmt(data, &mut s, &Default::default()));
+    Ok(s)
   }
 
   pub fn eval(&self, data: &HBData, out: &mut io::Write, eval_context: &EvalContext)  -> HBEvalResult {
     eval(&self, data, out, eval_context)
   }
+
+  /// renders into a `fmt::Write`, as a `String` or a `Formatter`
+  pub fn eval_fmt(&self, data: &HBData, out: &mut fmt::Write, eval_context: &EvalContext)  -> HBEvalResult {
+    eval_fmt(&self, data, out, eval_context)
+  }
+
+  /// `Display` of the template rendered with `data` and a default context.
+  ///
+  /// ```
+  /// use rumblebars::Template;
+  ///
+  /// let template = Template::new("Hello {{.}}!").unwrap();
+  /// assert_eq!(format!("{}", template.display(&"World")), "Hello World!");
+  /// ```
+  pub fn display<'a>(&'a self, data: &'a HBData) -> TemplateDisplay<'a> {
+    TemplateDisplay { template: self, data: data, eval_context: None }
+  }
+
+  /// `Display` of the template rendered with `data` and `eval_context`
+  pub fn display_with_context<'a>(&'a self, data: &'a HBData, eval_context: &'a EvalContext) -> TemplateDisplay<'a> {
+    TemplateDisplay { template: self, data: data, eval_context: Some(eval_context) }
+  }
+}
+
+/// Template and data, rendered when formatted. See [`Template::display()`](struct.Template.html#method.display).
+///
+/// Evaluation errors are reported as `fmt::Error`, use `Template::eval_fmt` to get them.
+pub struct TemplateDisplay<'a> {
+  template: &'a Template,
+  data: &'a HBData,
+  eval_context: Option<&'a EvalContext>,
+}
+
+impl <'a> fmt::Display for TemplateDisplay<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let res = match self.eval_context {
+      Some(eval_context) => self.template.eval_fmt(self.data, f, eval_context),
+      None => self.template.eval_fmt(self.data, f, &Default::default()),
+    };
+
+    res.map_err(|_| fmt::Error)
+  }
 }
 
 /// only used internaly
@@ -341,6 +507,7 @@ pub enum ParseError {
   InvalidExpression,
   UnmatchedBlock,
   UnexpectedBlockClose,
+  AmbiguousContext,
 }
 
 lazy_static! {
@@ -359,7 +526,8 @@ fn parse_hb_expression(exp: &str) -> Result<HBExpressionParsing, (ParseError, Op
     indent: None,
     no_leading_whitespace: false,
     no_trailing_whitespace: false,
-    inverse: false
+    inverse: false,
+    context: None,
   };
   let mut leading_whitespace = None;
   let mut trailing_whitespace = None;
@@ -453,12 +621,27 @@ fn parse_hb_expression(exp: &str) -> Result<HBExpressionParsing, (ParseError, Op
       options: options,
       render_options: render_options,
       block: None,
-      else_block: None
+      else_block: None,
+      block_source: None,
+      position: Position::default(),
     },
     trailing_whitespace
   ))
 }
 
+// name of a partial tag as written, none when bracketed
+fn partial_name_source(exp: &str) -> Option<String> {
+  let start = match exp.find('>') {
+    Some(i) => i + 1,
+    None => return None,
+  };
+  let name: String = exp[start..].trim_left_matches(|c| c == ' ' || c == '\t').chars()
+    .take_while(|c| !" \t\r\n~}".contains(*c))
+    .collect();
+
+  if name.is_empty() || name.contains('[') { None } else { Some(name) }
+}
+
 // after handling parsed token, handle result and leading/trailing whitespace
 #[derive(Debug)]
 enum Unit {
@@ -524,6 +707,60 @@ fn append_entry(stack: &mut Vec<(Box<Entries>, bool)>, e: Box<HBEntry>) {
 /// ```
 
 pub fn parse(template: &str) -> ParseResult {
+  let mut failed_at = 0;
+  parse_at(template, &mut failed_at)
+}
+
+/// Parse failure, with the position of the faulty expression.
+#[derive(Debug,Clone)]
+pub struct ParseFailure {
+  pub error: ParseError,
+  pub message: Option<String>,
+  /// byte offset in the template
+  pub offset: usize,
+  /// 1-based line number
+  pub line: usize,
+  /// 1-based column, in characters
+  pub column: usize,
+}
+
+impl fmt::Display for ParseFailure {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    try!(write!(f, "{}:{}: {:?}", self.line, self.column, self.error));
+    match self.message {
+      Some(ref message) => write!(f, ", {}", message),
+      None => Ok(()),
+    }
+  }
+}
+
+/// Parses a handlebars template as `parse()`, errors tell where parsing failed.
+///
+/// ```
+/// let failure = ::rumblebars::parse_detailed("<p>\n  {{#a}}{{/b}}").err().unwrap();
+/// assert_eq!((failure.line, failure.column), (2, 9));
+/// ```
+pub fn parse_detailed(template: &str) -> Result<Template, ParseFailure> {
+  let mut failed_at = 0;
+
+  parse_at(template, &mut failed_at).map_err(|(error, message)| {
+    // offset is in the trimmed template, that only lacks blanks before its last expression
+    let offset = ::std::cmp::min(failed_at, template.len());
+    let offset = template[offset..].find("{{").map(|i| offset + i).unwrap_or(offset);
+    let position = Positions::new(template).at(offset);
+
+    ParseFailure {
+      error: error,
+      message: message,
+      offset: offset,
+      line: position.line,
+      column: position.column,
+    }
+  })
+}
+
+// parses, keeping in `failed_at` the offset of the expression being parsed
+fn parse_at(template: &str, failed_at: &mut usize) -> ParseResult {
   // trimming template handling with a regex, as rustlex does not emit tokens on input end,
   // but it's very (very) convenient for this case
 
@@ -539,14 +776,40 @@ pub fn parse(template: &str) -> ParseResult {
   let mut previous_trail_whitespace: Option<(String, bool)> = None;
   let mut first = true;
 
+  // block sources tracking: template offset of opened blocks content
+  let mut position = 0;
+  let mut block_starts = vec![];
+
+  let mut positions = Positions::new(template);
+
   for tok in lexer {
+    let token_start = position;
+    position += tok.source().len();
+    // leading whitespace is part of expression tokens
+    *failed_at = token_start + tok.source().find("{{").unwrap_or(0);
+    let mut closed_block_source = None;
+
+    // tag position in the template, trimming only removes blanks before its last tag
+    let tag_position = match tok {
+      TokRaw(_) | TokEscaped(_) => Position::default(),
+      _ => {
+        let offset = ::std::cmp::min(*failed_at, template.len());
+        positions.at(template[offset..].find("{{").map(|i| offset + i).unwrap_or(offset))
+      },
+    };
+
     // handle each token specifities and distribute them to generic shift/reduce handlings
     let token_result = match tok {
       TokRaw(s) => {
         Unit::AppendRaw(Box::new(HBEntry::Raw(s)))
       },
+      TokEscaped(s) => {
+        // drop escaping backslash
+        Unit::AppendRaw(Box::new(HBEntry::Raw(s[1..].to_string())))
+      },
       TokSimpleExp(ref exp) => {
-        if let Ok((lead_wp, hb, trail_wp)) = parse_hb_expression(&exp) {
+        if let Ok((lead_wp, mut hb, trail_wp)) = parse_hb_expression(&exp) {
+          hb.position = tag_position;
           Unit::Append(lead_wp, Box::new(HBEntry::Eval(hb)), trail_wp)
         } else {
           return Result::Err((ParseError::InvalidExpression, Some(format!("Could not parse {:?}", exp))));
@@ -554,7 +817,8 @@ pub fn parse(template: &str) -> ParseResult {
       },
       TokCommentExp(ref exp) => {
         if let Ok((lead_wp, hb, trail_wp)) = parse_hb_expression(&exp) {
-          Unit::TrimOnly(lead_wp, Box::new(HBEntry::Eval(hb)), trail_wp)
+          let comment = HBComment { source: exp.trim().to_string(), render_options: hb.render_options, position: tag_position };
+          Unit::TrimOnly(lead_wp, Box::new(HBEntry::Comment(comment)), trail_wp)
         } else {
           return Result::Err((ParseError::InvalidExpression, Some(format!("Could not parse {:?}", exp))));
         }
@@ -562,13 +826,18 @@ pub fn parse(template: &str) -> ParseResult {
       TokNoEscapeExp(ref exp) => {
         if let Ok((lead_wp, mut hb, trail_wp)) = parse_hb_expression(&exp) {
           hb.render_options.escape = false;
+          hb.position = tag_position;
           Unit::Append(lead_wp, Box::new(HBEntry::Eval(hb)), trail_wp)
         } else {
           return Result::Err((ParseError::InvalidExpression, Some(format!("Could not parse {:?}", exp))));
         }
       },
       TokPartialExp(ref exp) => {
-        if let Ok((lead_wp, hb, trail_wp)) = parse_hb_expression(&exp) {
+        if let Ok((lead_wp, mut hb, trail_wp)) = parse_hb_expression(&exp) {
+          if let Some(name) = partial_name_source(&exp) {
+            hb.base = vec![name];
+          }
+          hb.position = tag_position;
           Unit::AppendAutoTrim(lead_wp, Box::new(HBEntry::Partial(hb)), trail_wp)
         } else {
           return Result::Err((ParseError::InvalidExpression, Some(format!("Could not parse {:?}", exp))));
@@ -577,6 +846,8 @@ pub fn parse(template: &str) -> ParseResult {
       TokBlockExp(ref exp, inverse) => {
         if let Ok((lead_wp, mut hb, trail_wp)) = parse_hb_expression(&exp) {
           hb.render_options.inverse = inverse;
+          hb.position = tag_position;
+          block_starts.push(token_start + exp.rfind("}}").map(|i| i + 2).unwrap_or(exp.len()));
           Unit::Shift(lead_wp, Box::new(HBEntry::Eval(hb)), false, trail_wp)
         } else {
           return Result::Err((ParseError::InvalidExpression, Some(format!("Could not parse {:?}", exp))));
@@ -591,6 +862,8 @@ pub fn parse(template: &str) -> ParseResult {
       },
       TokBlockEndExp(ref exp) => {
         if let Ok((lead_wp, hb, trail_wp)) = parse_hb_expression(&exp) {
+          let block_end = token_start + exp.find("{{").unwrap_or(0);
+          closed_block_source = block_starts.pop().map(|start| trimmed[start..::std::cmp::max(start, block_end)].to_string());
           Unit::Reduce(lead_wp, Box::new(HBEntry::Eval(hb)), trail_wp)
         } else {
           return Result::Err((ParseError::InvalidExpression, Some(format!("Could not parse {:?}", exp))));
@@ -614,6 +887,7 @@ pub fn parse(template: &str) -> ParseResult {
         let (remove_lead_wp, remove_trail_wp) = match *entry {
           HBEntry::Eval(ref exp) => (exp.render_options.no_leading_whitespace, exp.render_options.no_trailing_whitespace),
           HBEntry::Partial(ref exp) => (exp.render_options.no_leading_whitespace, exp.render_options.no_trailing_whitespace),
+          HBEntry::Comment(ref comment) => (comment.render_options.no_leading_whitespace, comment.render_options.no_trailing_whitespace),
           _ => (false, false),
         };
 
@@ -762,6 +1036,7 @@ pub fn parse(template: &str) -> ParseResult {
                       match pop {
                         (some_else, Some((block, _))) => {
                           parent.block = Some(block);
+                          parent.block_source = closed_block_source;
                           if let Some((else_block, _)) = some_else {
                             parent.else_block = Some(else_block);
                           }
@@ -796,6 +1071,9 @@ pub fn parse(template: &str) -> ParseResult {
             panic!("Should not reach: there's a bug in handelbars template@ parser, we're doing a block reduce on invalid parsing state");
           }
 
+        } else {
+          // comments only trim whitespace when rendering, they are kept for printing
+          append_entry(&mut stack, entry);
         }
       },
     }
@@ -811,6 +1089,8 @@ pub fn parse(template: &str) -> ParseResult {
     _ => ()
   };
 
+  *failed_at = template.len();
+
   if stack.len() > 0 {
     Result::Ok(Template { entries: *stack.remove(0).0 })
   } else {
@@ -875,7 +1155,7 @@ mod tests {
   #[test]
   fn hb_string_param() {
     match parse_hb_expression(r##"{{p "string"}}"##) {
-      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block}, _))  => {
+      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block, ..}, _))  => {
         assert_eq!(base, &vec!["p"]);
         assert_eq!(match params.get(0).unwrap() { &HBValHolder::String(ref s) => s.clone(), _ => "".to_string()}, "string".to_string());
       },
@@ -887,7 +1167,7 @@ mod tests {
   #[test]
   fn hb_prop_path_param() {
     match parse_hb_expression(r##"{{p some.path}}"##) {
-      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block}, _))  => {
+      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block, ..}, _))  => {
         assert_eq!(base, &vec!["p"]);
         assert_eq!(match params.get(0).unwrap() { &HBValHolder::Path(ref p) => p.clone(), _ => vec![]}, vec!["some", "path"]);
       },
@@ -899,7 +1179,7 @@ mod tests {
   #[test]
   fn hb_2_params() {
     match parse_hb_expression(r##"{{p some path}}"##) {
-      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block}, _))  => {
+      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block, ..}, _))  => {
         assert_eq!(base, &vec!["p"]);
         assert_eq!(match params.get(0).unwrap() { &HBValHolder::Path(ref p) => p.clone(), _ => vec![]}, vec!["some"]);
         assert_eq!(match params.get(1).unwrap() { &HBValHolder::Path(ref p) => p.clone(), _ => vec![]}, vec!["path"]);
@@ -912,7 +1192,7 @@ mod tests {
   #[test]
   fn hb_3_params() {
     match parse_hb_expression(r##"{{p some.path "with_string" yep}}"##) {
-      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block}, _))  => {
+      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block, ..}, _))  => {
         assert_eq!(base, &vec!["p"]);
         assert_eq!(match params.get(0).unwrap() { &HBValHolder::Path(ref p) => p.clone(), _ => vec![]}, vec!["some", "path"]);
         assert_eq!(match params.get(1).unwrap() { &HBValHolder::String(ref s) => s.clone(), _ => "".to_string()}, "with_string".to_string());
@@ -926,7 +1206,7 @@ mod tests {
   #[test]
   fn hb_full_feat_param() {
     match parse_hb_expression(r##"{{t "… param1" well.[that my baby].[1] ~}}"##) {
-      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block}, _))  => {
+      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block, ..}, _))  => {
         assert_eq!(base, &vec!["t"]);
         assert_eq!(match params.get(0).unwrap() { &HBValHolder::String(ref s) => s.clone(), _ => "".to_string()}, "… param1".to_string());
         assert_eq!(match params.get(1).unwrap() { &HBValHolder::Path(ref p) => p.clone(), _ => vec![]}, vec!["well", "that my baby", "1"]);
@@ -940,7 +1220,7 @@ mod tests {
   #[test]
   fn hb_option() {
     match parse_hb_expression(r##"{{t opt=u ~}}"##) {
-      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block}, _))  => {
+      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block, ..}, _))  => {
         assert_eq!(base, &vec!["t"]);
         assert_eq!(("opt".to_string(), vec!["u".to_string()]), match options.get(0).unwrap() {
           &(ref o, HBValHolder::Path(ref p)) => (o.clone(), p.clone()),
@@ -956,7 +1236,7 @@ mod tests {
   #[test]
   fn hb_mutli_options() {
     match parse_hb_expression(r##"{{t opt=u opt2="v" ~}}"##) {
-      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block}, _))  => {
+      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block, ..}, _))  => {
         assert_eq!(base, &vec!["t"]);
         assert_eq!(("opt".to_string(), vec!["u".to_string()]), match options.get(0).unwrap() {
           &(ref o, HBValHolder::Path(ref p)) => (o.clone(), p.clone()),
@@ -976,7 +1256,7 @@ mod tests {
   #[test]
   fn hb_param_options() {
     match parse_hb_expression(r##"{{t o.[t}+=] opt="v" ~}}"##) {
-      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block}, _))  => {
+      Ok((_, HBExpression{ref base, ref params, ref options, ref render_options, ref block, ref else_block, ..}, _))  => {
         assert_eq!(base, &vec!["t"]);
         assert_eq!(vec!["o", "t}+="], match params.get(0).unwrap() {
           &HBValHolder::Path(ref p) => p.clone(), _ => vec![]
@@ -1032,7 +1312,7 @@ mod tests {
     assert_eq!(true, match p.entries.get(0) {
       Some(& ref boxed_entry) => {
         match **boxed_entry {
-          HBEntry::Eval(HBExpression {ref base, ref params, ref options, ref render_options, ref block, ref else_block}) => match (block, else_block) { (&Some(_), &Some(_)) => true, _ => false },
+          HBEntry::Eval(HBExpression {ref base, ref params, ref options, ref render_options, ref block, ref else_block, ..}) => match (block, else_block) { (&Some(_), &Some(_)) => true, _ => false },
           _ => false,
         }
       },
@@ -1041,6 +1321,20 @@ mod tests {
   }
 
 
+  #[test]
+  fn parse_block_source() {
+    let p = parse("{{#outer}} a \\{{x}} {{#inner}}{{y}}{{/inner}}\n{{/outer}}").unwrap_or(Default::default());
+    assert_eq!(" a \\{{x}} {{#inner}}{{y}}{{/inner}}\n", match p.entries.get(0) {
+      Some(& ref boxed_entry) => {
+        match **boxed_entry {
+          HBEntry::Eval(HBExpression {block_source: Some(ref s), ..}) => &s[..],
+          _ => "",
+        }
+      }
+      _ => "",
+    });
+  }
+
   #[test]
   fn parse_exp_entangled() {
     let p = parse("tidi {{tada}} todo {{tudu}} bar").unwrap_or(Default::default());
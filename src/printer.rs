@@ -0,0 +1,230 @@
+use parse::{Entries, HBEntry, HBExpression, HBValHolder};
+
+/// Handlebars source of a syntax tree, parsing it back gives the same tree.
+///
+/// Block tags and comments that start a line are printed on their own line,
+/// that is dropped on parsing. They keep their indentation, unless they follow
+/// another tag line, that would take it as trailing whitespace. Comments are
+/// printed as written.
+///
+/// ```
+/// use rumblebars::{parse, to_source};
+///
+/// let template = parse("<ul>\n  {{#each items}}\n  <li>{{{~name~}}}</li> {{!-- item --}}\n  {{/each}}\n</ul>").unwrap();
+/// assert_eq!(to_source(&template.entries), "<ul>\n  {{#each items}}\n  <li>{{{~name~}}}</li> {{!-- item --}}\n  {{/each}}\n</ul>");
+/// ```
+pub fn to_source(entries: &Entries) -> String {
+  let mut printer = Printer::new(None);
+  printer.entries(entries);
+  printer.out
+}
+
+pub struct Printer {
+  pub out: String,
+  // indentation of standalone block tags for each level, their own when `None`
+  indent: Option<String>,
+  depth: usize,
+  // the last line is a standalone tag, whose trailing whitespace would take
+  // the indentation of the next line
+  tag_line: bool,
+}
+
+impl Printer {
+  pub fn new(indent: Option<String>) -> Printer {
+    Printer { out: String::new(), indent: indent, depth: 0, tag_line: false }
+  }
+
+  pub fn entries(&mut self, entries: &Entries) {
+    for entry in entries.iter() {
+      match **entry {
+        HBEntry::Raw(ref s) => {
+          self.out.push_str(&raw_source(s));
+          self.tag_line = self.tag_line && s.is_empty();
+        },
+        HBEntry::Eval(ref exp) if exp.block.is_some() => self.block(exp),
+        HBEntry::Eval(ref exp) => {
+          let tag = if exp.render_options.escape { tag("{{", exp, false, "}}") } else { tag("{{{", exp, false, "}}}") };
+          self.out.push_str(&tag);
+          self.tag_line = false;
+        },
+        HBEntry::Comment(ref comment) => {
+          let indent = self.tag_indent(&comment.render_options.indent);
+          self.standalone(&indent, &comment.source);
+        },
+        HBEntry::Partial(ref exp) => {
+          // standalone partials keep their indentation as raw text
+          let standalone = self.current_line() == exp.render_options.indent.as_ref().map(|s| &s[..]).unwrap_or("");
+
+          self.out.push_str(&tag("{{>", exp, true, "}}"));
+          if standalone {
+            self.out.push('\n');
+          }
+          self.tag_line = standalone;
+        },
+      }
+    }
+  }
+
+  fn block(&mut self, exp: &HBExpression) {
+    let indent = self.tag_indent(&exp.render_options.indent);
+    let path = path_source(&exp.base);
+
+    self.standalone(&indent, &tag(if exp.render_options.inverse { "{{^" } else { "{{#" }, exp, false, "}}"));
+    self.depth += 1;
+    if let Some(ref block) = exp.block {
+      self.entries(block);
+    }
+    if let Some(ref else_block) = exp.else_block {
+      self.depth -= 1;
+      self.standalone(&indent, "{{else}}");
+      self.depth += 1;
+      self.entries(else_block);
+    }
+    self.depth -= 1;
+    self.standalone(&indent, &format!("{{{{/{}}}}}", path));
+  }
+
+  // indentation of a standalone tag, `own` being the one it was parsed with
+  fn tag_indent(&self, own: &Option<String>) -> String {
+    match self.indent {
+      Some(ref unit) => ::std::iter::repeat(&unit[..]).take(self.depth).collect(),
+      None => own.clone().unwrap_or_default(),
+    }
+  }
+
+  // block tags starting a line are put alone on it, the line is removed on parsing
+  fn standalone(&mut self, indent: &str, tag: &str) {
+    if self.current_line().is_empty() {
+      if !self.tag_line {
+        self.out.push_str(indent);
+      }
+      self.out.push_str(tag);
+      self.out.push('\n');
+      self.tag_line = true;
+    } else {
+      self.out.push_str(tag);
+      self.tag_line = false;
+    }
+  }
+
+  fn current_line(&self) -> &str {
+    &self.out[self.out.rfind('\n').map(|i| i + 1).unwrap_or(0)..]
+  }
+}
+
+// `{{` and backslashes that would escape what follows are escaped
+fn raw_source(s: &str) -> String {
+  let mut source = String::new();
+  let mut rest = s;
+
+  while let Some(c) = rest.chars().next() {
+    let count = rest.len() - rest.trim_left_matches('\\').len();
+    if count > 0 {
+      source.push_str(&backslashes_source(count, rest[count..].chars().next()));
+      rest = &rest[count..];
+    } else if rest.starts_with("{{") {
+      source.push_str("\\{{");
+      rest = &rest[2..];
+    } else {
+      source.push(c);
+      rest = &rest[c.len_utf8()..];
+    }
+  }
+
+  source
+}
+
+// `count` backslashes followed by `next`. Parsing drops one backslash out of
+// two, but keeps both of the last two when text follows them.
+fn backslashes_source(count: usize, next: Option<char>) -> String {
+  let escaped = match next {
+    Some(c) if c != '{' && c != '\r' && c != '\n' => count * 2 - 1,
+    _ => count * 2,
+  };
+  ::std::iter::repeat("\\").take(escaped).collect()
+}
+
+fn tag(open: &str, exp: &HBExpression, partial: bool, close: &str) -> String {
+  let mut tag = String::new();
+  let options = &exp.render_options;
+
+  // `{{~#`, `{{~>` and `{{{~`
+  if !options.no_leading_whitespace {
+    tag.push_str(open);
+  } else if open == "{{{" {
+    tag.push_str("{{{~");
+  } else {
+    tag.push_str("{{~");
+    tag.push_str(&open[2..]);
+  }
+  if partial {
+    tag.push(' ');
+  }
+
+  if partial {
+    tag.push_str(&partial_source(&exp.partial_name()));
+  } else {
+    tag.push_str(&path_source(&exp.base));
+  }
+  for param in exp.params.iter() {
+    tag.push(' ');
+    tag.push_str(&value_source(param));
+  }
+  for &(ref name, ref value) in exp.options.iter() {
+    tag.push(' ');
+    tag.push_str(name);
+    tag.push('=');
+    tag.push_str(&value_source(value));
+  }
+
+  if options.no_trailing_whitespace {
+    tag.push('~');
+  }
+  tag.push_str(close);
+  tag
+}
+
+pub fn value_source(value: &HBValHolder) -> String {
+  match *value {
+    HBValHolder::String(ref s) => format!("\"{}\"", s),
+    HBValHolder::Path(ref path) => path_source(path),
+    HBValHolder::Literal(_, ref source) => source.clone(),
+  }
+}
+
+// keys are `.` separated, `/` after `.` and `..`
+pub fn path_source(path: &[String]) -> String {
+  let mut source = String::new();
+
+  for (i, key) in path.iter().enumerate() {
+    if i > 0 {
+      let after_alias = path[i - 1] == "." || path[i - 1] == "..";
+      source.push(if after_alias { '/' } else { '.' });
+    }
+    source.push_str(&key_source(key));
+  }
+
+  source
+}
+
+// partial names are kept as written, bracketed when they would not parse back
+pub fn partial_source(name: &str) -> String {
+  if name.is_empty() || name.contains(|c: char| " \t\r\n~}[]\"".contains(c)) {
+    format!("[{}]", name)
+  } else {
+    name.to_string()
+  }
+}
+
+// keys that are not identifiers are bracketed
+fn key_source(key: &str) -> String {
+  let identifier = !key.is_empty() && key != "this" && key.char_indices().all(|(i, c)| {
+    (c == '@' && i == 0) || !"!\"#%&'()*+,./;<=>@[\\]^`{|}~ \t".contains(c)
+  });
+
+  if key == "." || key == ".." || identifier {
+    key.to_string()
+  } else {
+    format!("[{}]", key)
+  }
+}
@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use serialize::json::Json;
+
+use parse::{Entries, HBEntry, HBExpression, HBValHolder, Position, Template};
+use eval::{EvalContext, HBData, HBNodeType, value_for_key_path_in_context};
+use analysis::{PathUsage, scope_label};
+use printer::path_source;
+
+/// Template path that resolves to nothing in a data document
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingPath {
+  /// partial the path is read in, none for the checked template
+  pub partial: Option<String>,
+  /// 1-based line of the expression that first reads the path, in the
+  /// source of its partial if any
+  pub line: usize,
+  /// 1-based column of the expression, in characters
+  pub column: usize,
+  pub usage: PathUsage,
+}
+
+/// `path in scope at line:column` or `path in scope at line:column, in partial name`
+impl fmt::Display for MissingPath {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    try!(write!(f, "{} at {}:{}", self.usage, self.line, self.column));
+    match self.partial {
+      Some(ref partial) => write!(f, ", in partial {}", partial),
+      None => Ok(()),
+    }
+  }
+}
+
+/// How a data document fits the needs of a template, see `check_data()`
+#[derive(Debug, Default)]
+pub struct DataReport {
+  /// paths that resolve to nothing, wherever they are read
+  pub missing: Vec<MissingPath>,
+  /// data paths that are never read, sorted, array items sharing the path of
+  /// their array. Only the outermost unused path is given, `author` rather
+  /// than `author.name`.
+  pub unused: Vec<String>,
+}
+
+impl DataReport {
+  /// no path of the template is missing from the data
+  pub fn is_valid(&self) -> bool {
+    self.missing.is_empty()
+  }
+}
+
+/// Checks `data` against the paths `template` reads, without rendering it.
+///
+/// Every branch is walked: both blocks of `if` and `unless`, each item of
+/// `each` and of list sections, and registered partials. Paths are looked up
+/// as on evaluation, a path is missing when it resolves to nothing everywhere
+/// it is read. Paths under a block whose value is missing or empty are not
+/// checked. Helpers other than the builtin ones are not called, their blocks
+/// are walked with the current context.
+///
+/// ```
+/// extern crate rustc_serialize as serialize;
+/// extern crate rumblebars;
+/// # fn main() {
+/// use serialize::json::Json;
+/// use rumblebars::{parse, EvalContext};
+///
+/// let template = parse("Hello {{user.name}}, {{#each orders}}{{id}} {{totl}}{{/each}}").unwrap();
+/// let data = Json::from_str(r##"{"user": {"name": "N", "email": "e"}, "orders": [{"id": 1, "total": 2}]}"##).unwrap();
+///
+/// let report = template.check_data(&data, &EvalContext::new());
+///
+/// assert_eq!(report.missing[0].to_string(), "totl in each orders at 1:45");
+/// assert_eq!(report.unused, vec!["orders.total", "user.email"]);
+/// # }
+/// ```
+pub fn check_data(template: &Template, data: &Json, eval_context: &EvalContext) -> DataReport {
+  let mut globals: HashMap<&str, &HBData> = HashMap::new();
+  globals.insert("@root", data);
+
+  let mut checker = Checker {
+    context: eval_context,
+    globals: globals,
+    reads: BTreeMap::new(),
+    used: HashSet::new(),
+    partial: None,
+    partials: Vec::new(),
+    scope: Vec::new(),
+  };
+  let root: &HBData = data;
+  checker.entries(&template.entries, root, &vec![root]);
+
+  let mut all = BTreeSet::new();
+  let mut used = BTreeSet::new();
+  used.insert(Vec::new());
+  data_paths(data, &mut Vec::new(), &checker.used, &mut all, &mut used);
+
+  DataReport {
+    missing: checker.reads.into_iter().filter(|&(_, (resolved, _))| !resolved).map(|((partial, usage), (_, position))| {
+      MissingPath { partial: partial, line: position.line, column: position.column, usage: usage }
+    }).collect(),
+    unused: all.iter()
+      .filter(|path| !used.contains(*path) && used.contains(&path[..path.len() - 1].to_vec()))
+      .map(|path| path_source(path))
+      .collect(),
+  }
+}
+
+struct Checker<'a> {
+  context: &'a EvalContext,
+  globals: HashMap<&'static str, &'a HBData>,
+  // whether each path resolved at least once, and where it is first read
+  reads: BTreeMap<(Option<String>, PathUsage), (bool, Position)>,
+  // addresses of resolved data nodes
+  used: HashSet<usize>,
+  partial: Option<String>,
+  // partials being checked, recursive ones are checked once
+  partials: Vec<String>,
+  scope: Vec<String>,
+}
+
+impl<'a> Checker<'a> {
+  fn entries(&mut self, entries: &Entries, data: &'a HBData, stack: &Vec<&'a HBData>) {
+    for entry in entries.iter() {
+      match **entry {
+        HBEntry::Raw(_) | HBEntry::Comment(_) => (),
+        HBEntry::Eval(ref exp) => self.expression(exp, data, stack),
+        HBEntry::Partial(ref exp) => self.partial(exp, data, stack),
+      }
+    }
+  }
+
+  // `@index` like variables are not data paths
+  fn read(&mut self, path: &[String], position: Position, data: &'a HBData, stack: &Vec<&'a HBData>) -> Option<&'a HBData> {
+    match path.first() {
+      Some(key) if key.starts_with("@") && key != "@root" => return None,
+      _ => (),
+    }
+
+    let value = value_for_key_path_in_context(data, path, stack, &self.globals, self.context.compat);
+    let read = self.reads.entry((self.partial.clone(), PathUsage { path: path.to_vec(), scope: self.scope.clone() })).or_insert((false, position));
+    read.0 = read.0 || value.is_some();
+
+    if let Some(value) = value {
+      self.used.insert(address(value));
+    }
+    value
+  }
+
+  // values of the parameters, options are read for their paths only
+  fn values(&mut self, exp: &HBExpression, data: &'a HBData, stack: &Vec<&'a HBData>) -> Vec<Option<&'a HBData>> {
+    for &(_, ref value) in exp.options.iter() {
+      if let HBValHolder::Path(ref path) = *value {
+        self.read(path, exp.position, data, stack);
+      }
+    }
+
+    exp.params.iter().map(|param| match *param {
+      HBValHolder::Path(ref path) => self.read(path, exp.position, data, stack),
+      _ => None,
+    }).collect()
+  }
+
+  // block content in a new context, once per item for lists
+  fn scoped(&mut self, exp: &HBExpression, block: &Entries, value: &'a HBData, stack: &Vec<&'a HBData>) {
+    self.scope.push(scope_label("", exp, false));
+    match value.typed_node() {
+      HBNodeType::Array(_) => for item in value.values() {
+        self.entries(block, item, stack);
+      },
+      HBNodeType::Null => (),
+      _ => self.entries(block, value, stack),
+    }
+    self.scope.pop();
+  }
+
+  fn expression(&mut self, exp: &HBExpression, data: &'a HBData, stack: &Vec<&'a HBData>) {
+    let registered = exp.base.len() == 1 && self.context.has_helper_with_name(&exp.base[0]);
+    let helper = registered || !exp.params.is_empty() || !exp.options.is_empty();
+
+    let values = self.values(exp, data, stack);
+    let value = if helper { None } else { self.read(&exp.base, exp.position, data, stack) };
+
+    let block = match exp.block {
+      Some(ref block) => block,
+      None => return,
+    };
+
+    if helper {
+      // helpers render their blocks without stacking the current context
+      match (&exp.base[0][..], values.first().and_then(|v| *v)) {
+        ("if", _) | ("unless", _) => self.entries(block, data, stack),
+        ("each", Some(items)) if registered => match items.typed_node() {
+          HBNodeType::Branch(_) => {
+            self.scope.push(scope_label("", exp, false));
+            for item in items.values() {
+              self.entries(block, item, stack);
+            }
+            self.scope.pop();
+          },
+          _ => self.scoped(exp, block, items, stack),
+        },
+        ("with", Some(value)) if registered => self.scoped(exp, block, value, stack),
+        ("each", None) | ("with", None) => (),
+        _ => {
+          self.scope.push(scope_label("", exp, false));
+          self.entries(block, data, stack);
+          self.scope.pop();
+        },
+      }
+    } else if exp.render_options.inverse {
+      self.entries(block, data, stack);
+    } else if let Some(value) = value {
+      let mut section_stack = stack.clone();
+      section_stack.push(data);
+      self.scoped(exp, block, value, &section_stack);
+    }
+
+    if let Some(ref else_block) = exp.else_block {
+      self.entries(else_block, data, stack);
+    }
+  }
+
+  fn partial(&mut self, exp: &HBExpression, data: &'a HBData, stack: &Vec<&'a HBData>) {
+    let name = exp.partial_name();
+    let values = self.values(exp, data, stack);
+
+    let context = self.context;
+    let template = match context.partial_with_name(&name) {
+      Some(template) if !self.partials.contains(&name) => template,
+      _ => return,
+    };
+
+    let scoped = !exp.params.is_empty();
+    if scoped {
+      self.scope.push(scope_label("> ", exp, true));
+    }
+    let parent = ::std::mem::replace(&mut self.partial, Some(name.clone()));
+    self.partials.push(name);
+
+    self.entries(&template.entries, values.first().and_then(|v| *v).unwrap_or(data), stack);
+
+    self.partials.pop();
+    self.partial = parent;
+    if scoped {
+      self.scope.pop();
+    }
+  }
+}
+
+fn address(data: &HBData) -> usize {
+  data as *const _ as *const u8 as usize
+}
+
+// paths of the document, and the ones leading to resolved nodes
+fn data_paths(json: &Json, path: &mut Vec<String>, resolved: &HashSet<usize>, all: &mut BTreeSet<Vec<String>>, used: &mut BTreeSet<Vec<String>>) {
+  if !path.is_empty() {
+    all.insert(path.clone());
+  }
+  if resolved.contains(&address(json)) {
+    for i in 1..path.len() + 1 {
+      used.insert(path[..i].to_vec());
+    }
+  }
+
+  match *json {
+    Json::Object(ref object) => for (key, value) in object.iter() {
+      path.push(key.clone());
+      data_paths(value, path, resolved, all, used);
+      path.pop();
+    },
+    Json::Array(ref items) => for item in items.iter() {
+      data_paths(item, path, resolved, all, used);
+    },
+    _ => (),
+  }
+}
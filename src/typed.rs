@@ -0,0 +1,196 @@
+use std::io;
+use std::fmt;
+use std::marker::PhantomData;
+use std::collections::{HashMap, BTreeMap, VecDeque};
+use std::hash::Hash;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use serialize::json::Json;
+
+use parse::{Template, TemplateDisplay};
+use eval::{HBData, HBEvalResult, EvalContext};
+
+/// A template bound to the type of the data it renders.
+///
+/// Typed templates are made by the `typed_template!` macro of `rumblebars_macros`,
+/// that checks at compile time every path of the template against the fields of
+/// the data type, as keyed by `#[derive(HBData)]`. A path to a missing key is a
+/// compiler error.
+///
+/// `{{#each path}}` renders its block with items of `path` (see `HBItems`),
+/// `{{#with path}}` with the value of `path`, `../` goes back to the enclosing
+/// data and `@root` to the rendered data. Blocks of other helpers and of mustache
+/// sections are not checked, nor are partials, only their parameters.
+///
+/// Paths go through derived structs and options of them (see `HBField`). Keys of
+/// json values, maps and derived enums are only known at runtime, paths into them
+/// are not checked. Helpers called without parameters, as `{{csrf_token}}`, are
+/// declared with `helpers = [...]`, they would be checked as paths otherwise.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[derive(HBData)]
+/// struct Post { title: String, tags: Vec<String> }
+///
+/// let page = typed_template!(Post, "<h1>{{title}}</h1>{{#each tags}}{{.}} {{/each}}");
+/// let html = page.eval_to_string(&post).unwrap();
+///
+/// // error: the trait bound `Post: HBField<(KeyChar<'t'>, KeyChar<'i'>, KeyChar<'l'>, KeyChar<'t'>, KeyChar<'e'>)>` is not satisfied
+/// let broken = typed_template!(Post, "<h1>{{tilte}}</h1>");
+///
+/// let form = typed_template!(Post, helpers = [csrf_token], "<input value=\"{{csrf_token}}\">{{title}}");
+/// ```
+pub struct TypedTemplate<T> {
+  template: Template,
+  data: PhantomData<fn(&T)>,
+}
+
+impl <T: HBData> TypedTemplate<T> {
+  /// binds `template` to `T` without checking it, use `typed_template!` for checked templates
+  pub fn new(template: Template) -> TypedTemplate<T> {
+    TypedTemplate { template: template, data: PhantomData }
+  }
+
+  pub fn template(&self) -> &Template {
+    &self.template
+  }
+
+  pub fn into_template(self) -> Template {
+    self.template
+  }
+
+  pub fn eval(&self, data: &T, out: &mut io::Write, eval_context: &EvalContext) -> HBEvalResult {
+    self.template.eval(data as &HBData, out, eval_context)
+  }
+
+  pub fn eval_fmt(&self, data: &T, out: &mut fmt::Write, eval_context: &EvalContext) -> HBEvalResult {
+    self.template.eval_fmt(data as &HBData, out, eval_context)
+  }
+
+  pub fn eval_to_string(&self, data: &T) -> io::Result<String> {
+    self.template.eval_to_string(data as &HBData)
+  }
+
+  pub fn display<'a>(&'a self, data: &'a T) -> TemplateDisplay<'a> {
+    self.template.display(data as &HBData)
+  }
+}
+
+/// Values iterated by `{{#each}}` in typed templates, `Item` is the data of the
+/// block.
+pub trait HBItems {
+  type Item: ?Sized;
+
+  fn nth_item(&self, n: usize) -> Option<&Self::Item>;
+}
+
+impl <T> HBItems for Vec<T> {
+  type Item = T;
+  fn nth_item(&self, n: usize) -> Option<&T> { self.get(n) }
+}
+
+impl <T> HBItems for [T] {
+  type Item = T;
+  fn nth_item(&self, n: usize) -> Option<&T> { self.get(n) }
+}
+
+impl <T> HBItems for VecDeque<T> {
+  type Item = T;
+  fn nth_item(&self, n: usize) -> Option<&T> { self.get(n) }
+}
+
+impl <K: Eq + Hash, V> HBItems for HashMap<K, V> {
+  type Item = V;
+  fn nth_item(&self, n: usize) -> Option<&V> { self.values().nth(n) }
+}
+
+impl <K: Ord, V> HBItems for BTreeMap<K, V> {
+  type Item = V;
+  fn nth_item(&self, n: usize) -> Option<&V> { self.values().nth(n) }
+}
+
+impl HBItems for Json {
+  type Item = Json;
+  fn nth_item(&self, n: usize) -> Option<&Json> {
+    match *self {
+      Json::Array(ref a) => a.get(n),
+      Json::Object(ref o) => o.values().nth(n),
+      _ => None,
+    }
+  }
+}
+
+impl <'a, T: HBItems + ?Sized> HBItems for &'a T {
+  type Item = T::Item;
+  fn nth_item(&self, n: usize) -> Option<&T::Item> { (**self).nth_item(n) }
+}
+
+impl <T: HBItems + ?Sized> HBItems for Box<T> {
+  type Item = T::Item;
+  fn nth_item(&self, n: usize) -> Option<&T::Item> { (**self).nth_item(n) }
+}
+
+impl <T: HBItems> HBItems for Option<T> {
+  type Item = T::Item;
+  fn nth_item(&self, n: usize) -> Option<&T::Item> { self.as_ref().and_then(|items| items.nth_item(n)) }
+}
+
+/// Character of a key, keys are tuples of them in `HBField`, `(KeyChar<'i'>, KeyChar<'d'>)`
+/// for `id`.
+pub struct KeyChar<const C: char>;
+
+/// Type of the data at `Key`, for paths checked by `typed_template!`.
+///
+/// `#[derive(HBData)]` implements it for each key of structs with named fields.
+/// Json values and maps accept every key, their items being the data. Derived
+/// enums accept every key as `UncheckedData`, the variant being only known at
+/// runtime.
+pub trait HBField<Key> {
+  type Value: ?Sized;
+}
+
+/// Data at a key that can't be checked at compile time, see `HBField`
+pub struct UncheckedData;
+
+impl <Key> HBField<Key> for UncheckedData {
+  type Value = UncheckedData;
+}
+
+impl HBItems for UncheckedData {
+  type Item = UncheckedData;
+  fn nth_item(&self, _: usize) -> Option<&UncheckedData> { None }
+}
+
+impl <Key> HBField<Key> for Json {
+  type Value = Json;
+}
+
+impl <Key, K: Eq + Hash, V> HBField<Key> for HashMap<K, V> {
+  type Value = V;
+}
+
+impl <Key, K: Ord, V> HBField<Key> for BTreeMap<K, V> {
+  type Value = V;
+}
+
+impl <Key, T: HBField<Key>> HBField<Key> for Option<T> {
+  type Value = T::Value;
+}
+
+impl <'a, Key, T: HBField<Key> + ?Sized> HBField<Key> for &'a T {
+  type Value = T::Value;
+}
+
+impl <Key, T: HBField<Key> + ?Sized> HBField<Key> for Box<T> {
+  type Value = T::Value;
+}
+
+impl <Key, T: HBField<Key> + ?Sized> HBField<Key> for Rc<T> {
+  type Value = T::Value;
+}
+
+impl <Key, T: HBField<Key> + ?Sized> HBField<Key> for Arc<T> {
+  type Value = T::Value;
+}
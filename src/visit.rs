@@ -0,0 +1,198 @@
+use parse::{Entries, HBEntry, HBExpression, HBValHolder, HBComment};
+
+/// Walks a syntax tree by reference.
+///
+/// Every method defaults to walking its children, with the matching `walk_*`
+/// function, overriding one stops the walk there unless it calls it back.
+///
+/// ```
+/// use rumblebars::{parse, Visitor, HBExpression, walk_expression};
+///
+/// struct Helpers(Vec<String>);
+///
+/// impl Visitor for Helpers {
+///   fn visit_expression(&mut self, exp: &HBExpression) {
+///     if exp.block.is_some() || !exp.params.is_empty() {
+///       self.0.push(exp.path());
+///     }
+///     walk_expression(self, exp);
+///   }
+/// }
+///
+/// let template = parse("{{#each items}}{{link url title}}{{/each}}").unwrap();
+/// let mut helpers = Helpers(Vec::new());
+/// helpers.visit_entries(&template.entries);
+///
+/// assert_eq!(helpers.0, vec!["each", "link"]);
+/// ```
+pub trait Visitor {
+  fn visit_entries(&mut self, entries: &Entries) {
+    walk_entries(self, entries)
+  }
+
+  fn visit_entry(&mut self, entry: &HBEntry) {
+    walk_entry(self, entry)
+  }
+
+  fn visit_raw(&mut self, _raw: &str) {
+  }
+
+  /// `{{! comment }}`, that renders nothing
+  fn visit_comment(&mut self, _comment: &HBComment) {
+  }
+
+  /// `{{expression}}` and blocks, with their content
+  fn visit_expression(&mut self, exp: &HBExpression) {
+    walk_expression(self, exp)
+  }
+
+  /// `{{> partial}}`, see `HBExpression::partial_name()`
+  fn visit_partial(&mut self, exp: &HBExpression) {
+    walk_expression(self, exp)
+  }
+
+  /// parameters and options values
+  fn visit_value(&mut self, value: &HBValHolder) {
+    walk_value(self, value)
+  }
+
+  /// data paths given as values
+  fn visit_path(&mut self, _path: &[String]) {
+  }
+}
+
+pub fn walk_entries<V: Visitor + ?Sized>(visitor: &mut V, entries: &Entries) {
+  for entry in entries.iter() {
+    visitor.visit_entry(entry);
+  }
+}
+
+pub fn walk_entry<V: Visitor + ?Sized>(visitor: &mut V, entry: &HBEntry) {
+  match *entry {
+    HBEntry::Raw(ref s) => visitor.visit_raw(s),
+    HBEntry::Eval(ref exp) => visitor.visit_expression(exp),
+    HBEntry::Partial(ref exp) => visitor.visit_partial(exp),
+    HBEntry::Comment(ref comment) => visitor.visit_comment(comment),
+  }
+}
+
+/// visits values, then the block and the else block
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, exp: &HBExpression) {
+  for param in exp.params.iter() {
+    visitor.visit_value(param);
+  }
+  for &(_, ref value) in exp.options.iter() {
+    visitor.visit_value(value);
+  }
+  if let Some(ref block) = exp.block {
+    visitor.visit_entries(block);
+  }
+  if let Some(ref else_block) = exp.else_block {
+    visitor.visit_entries(else_block);
+  }
+}
+
+pub fn walk_value<V: Visitor + ?Sized>(visitor: &mut V, value: &HBValHolder) {
+  if let HBValHolder::Path(ref path) = *value {
+    visitor.visit_path(path);
+  }
+}
+
+/// Rebuilds a syntax tree, taking it by value.
+///
+/// Every method defaults to folding its children, with the matching
+/// `noop_fold_*` function, and rebuilding the node around them.
+///
+/// ```
+/// use rumblebars::{parse, to_source, Fold, HBExpression, noop_fold_expression};
+///
+/// struct Rename;
+///
+/// impl Fold for Rename {
+///   fn fold_expression(&mut self, mut exp: HBExpression) -> HBExpression {
+///     if exp.base == ["bold"] {
+///       exp.base = vec!["strong".to_string()];
+///     }
+///     noop_fold_expression(self, exp)
+///   }
+/// }
+///
+/// let template = parse("{{#bold}}{{bold name}}{{/bold}}").unwrap();
+/// let entries = Rename.fold_entries(template.entries);
+///
+/// assert_eq!(to_source(&entries), "{{#strong}}{{strong name}}{{/strong}}");
+/// ```
+pub trait Fold {
+  fn fold_entries(&mut self, entries: Entries) -> Entries {
+    noop_fold_entries(self, entries)
+  }
+
+  fn fold_entry(&mut self, entry: HBEntry) -> HBEntry {
+    noop_fold_entry(self, entry)
+  }
+
+  fn fold_raw(&mut self, raw: String) -> String {
+    raw
+  }
+
+  /// `{{! comment }}`, that renders nothing
+  fn fold_comment(&mut self, comment: HBComment) -> HBComment {
+    comment
+  }
+
+  /// `{{expression}}` and blocks, with their content
+  fn fold_expression(&mut self, exp: HBExpression) -> HBExpression {
+    noop_fold_expression(self, exp)
+  }
+
+  /// `{{> partial}}`, see `HBExpression::partial_name()`
+  fn fold_partial(&mut self, exp: HBExpression) -> HBExpression {
+    noop_fold_expression(self, exp)
+  }
+
+  /// parameters and options values
+  fn fold_value(&mut self, value: HBValHolder) -> HBValHolder {
+    noop_fold_value(self, value)
+  }
+
+  /// data paths given as values
+  fn fold_path(&mut self, path: Vec<String>) -> Vec<String> {
+    path
+  }
+}
+
+pub fn noop_fold_entries<F: Fold + ?Sized>(folder: &mut F, entries: Entries) -> Entries {
+  entries.into_iter().map(|entry| Box::new(folder.fold_entry(*entry))).collect()
+}
+
+pub fn noop_fold_entry<F: Fold + ?Sized>(folder: &mut F, entry: HBEntry) -> HBEntry {
+  match entry {
+    HBEntry::Raw(s) => HBEntry::Raw(folder.fold_raw(s)),
+    HBEntry::Eval(exp) => HBEntry::Eval(folder.fold_expression(exp)),
+    HBEntry::Partial(exp) => HBEntry::Partial(folder.fold_partial(exp)),
+    HBEntry::Comment(comment) => HBEntry::Comment(folder.fold_comment(comment)),
+  }
+}
+
+/// folds values, then the block and the else block
+pub fn noop_fold_expression<F: Fold + ?Sized>(folder: &mut F, exp: HBExpression) -> HBExpression {
+  let HBExpression { base, params, options, render_options, block, else_block, block_source, position } = exp;
+
+  HBExpression {
+    base: base,
+    params: params.into_iter().map(|param| folder.fold_value(param)).collect(),
+    options: options.into_iter().map(|(name, value)| (name, folder.fold_value(value))).collect(),
+    render_options: render_options,
+    block: block.map(|block| Box::new(folder.fold_entries(*block))),
+    else_block: else_block.map(|block| Box::new(folder.fold_entries(*block))),
+    block_source: block_source,
+    position: position,
+  }
+}
+
+pub fn noop_fold_value<F: Fold + ?Sized>(folder: &mut F, value: HBValHolder) -> HBValHolder {
+  match value {
+    HBValHolder::Path(path) => HBValHolder::Path(folder.fold_path(path)),
+    value => value,
+  }
+}
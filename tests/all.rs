@@ -1,11 +1,26 @@
 extern crate rumblebars;
 extern crate rustc_serialize as serialize;
+#[macro_use] extern crate rumblebars_macros;
+extern crate rumblebars_precompile_fixture as fixture;
 
 #[cfg(feature = "stream_test")] extern crate rand;
 #[cfg(feature = "stream_test")] extern crate time;
 
 mod helpers;
 mod parse;
+mod derive;
+mod data;
+mod escape;
+mod loader;
+mod precompile;
+mod template;
+mod format;
+mod visit;
+mod analysis;
+mod lint;
+mod graph;
+mod shape;
+#[cfg(feature = "serialization")] mod cache;
 
 mod eval {
   mod trimming;
@@ -16,6 +31,7 @@ mod eval {
   use std::default::Default;
 
   use rumblebars::eval;
+  use rumblebars::eval_fmt;
   use rumblebars::parse;
   use rumblebars::EvalContext;
 
@@ -368,6 +384,114 @@ mod eval {
     assert_eq!(String::from_utf8(buf).unwrap(), "<script lang=\"text/javascript\">pawned()</script>");
   }
 
+  #[test]
+  fn fmt_render() {
+    let json = Json::from_str(r##"{"p": "poney <3", "l": ["é", "à"]}"##).ok().unwrap();
+    let tmpl = parse(r##"{{p}} {{#l}}{{.}}{{/l}}"##).ok().unwrap();
+    let mut s = String::new();
+
+    eval_fmt(&tmpl, &json, &mut s, &Default::default()).unwrap();
+
+    assert_eq!(s, "poney &lt;3 éà");
+    assert_eq!(tmpl.eval_to_string(&json).unwrap(), "poney &lt;3 éà");
+  }
+
+  #[test]
+  fn display() {
+    let json = Json::from_str(r##"{"p": "poney"}"##).ok().unwrap();
+    let tmpl = parse(r##"{{p}}!"##).ok().unwrap();
+    let mut eval_ctxt: EvalContext = Default::default();
+
+    eval_ctxt.register_partial("p".to_string(), parse("{{p}}?").ok().unwrap());
+
+    assert_eq!(format!("<{}>", tmpl.display(&json)), "<poney!>");
+    assert_eq!(format!("{}", parse("{{>p}}").ok().unwrap().display_with_context(&json, &eval_ctxt)), "poney?");
+  }
+
+  #[test]
+  fn eval_to_string_error() {
+    let tmpl = parse(r##"{{fail}}"##).ok().unwrap();
+    let mut data = ::std::collections::BTreeMap::new();
+
+    data.insert("fail".to_string(), ::rumblebars::Lambda::new(|_, _| {
+      Err(::std::io::Error::new(::std::io::ErrorKind::Other, "lambda failure"))
+    }));
+
+    let err = tmpl.eval_to_string(&data).unwrap_err();
+    assert_eq!(err.to_string(), "lambda failure");
+  }
+
+  #[test]
+  fn eval_fmt_error() {
+    let tmpl = parse(r##"{{fail}}"##).ok().unwrap();
+    let mut eval_ctxt: EvalContext = Default::default();
+    let mut s = String::new();
+
+    eval_ctxt.register_helper("fail".to_string(), Box::new(|_, _, _, _| {
+      Err(::std::io::Error::new(::std::io::ErrorKind::Other, "helper failure"))
+    }));
+
+    let err = tmpl.eval_fmt(&Json::Null, &mut s, &eval_ctxt).unwrap_err();
+    assert_eq!(err.to_string(), "helper failure");
+  }
+
+  #[test]
+  fn render_by_name() {
+    let json = Json::from_str(r##"{"title": "Home", "items": ["a", "b"]}"##).ok().unwrap();
+    let mut eval_ctxt: EvalContext = Default::default();
+
+    eval_ctxt.register_partial("layout/header".to_string(), parse("<h1>{{title}}</h1>").ok().unwrap());
+    eval_ctxt.register_partial("item".to_string(), parse("<li>{{.}}</li>").ok().unwrap());
+    eval_ctxt.register_partial("home".to_string(), parse("{{> layout/header}}<ul>{{#each items}}{{> item}}{{/each}}</ul>").ok().unwrap());
+
+    assert_eq!(eval_ctxt.render_to_string("home", &json).unwrap(), "<h1>Home</h1><ul><li>a</li><li>b</li></ul>");
+    assert_eq!(eval_ctxt.render_to_string("item", &"c").unwrap(), "<li>c</li>");
+  }
+
+  #[test]
+  fn render_by_name_errors() {
+    let mut eval_ctxt: EvalContext = Default::default();
+
+    eval_ctxt.register_helper("fail".to_string(), Box::new(|_, _, _, _| {
+      Err(::std::io::Error::new(::std::io::ErrorKind::Other, "helper failure"))
+    }));
+    eval_ctxt.register_partial("page".to_string(), parse("{{fail}}").ok().unwrap());
+
+    let err = eval_ctxt.render_to_string("page", &Json::Null).unwrap_err();
+    assert_eq!(err.to_string(), "template ‘page’: helper failure");
+
+    // the innermost partial is named too
+    eval_ctxt.register_partial("layout".to_string(), parse("<div>{{#if ok}}{{> page}}{{/if}}</div>").ok().unwrap());
+    let err = eval_ctxt.render_to_string("layout", &Json::from_str(r##"{"ok": true}"##).unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "template ‘layout’: template ‘page’: helper failure");
+
+    let err = eval_ctxt.render_to_string("nope", &Json::Null).unwrap_err();
+    assert_eq!(err.kind(), ::std::io::ErrorKind::NotFound);
+    assert_eq!(err.to_string(), "no template named ‘nope’");
+  }
+
+  #[test]
+  fn child_context() {
+    use std::rc::Rc;
+    use rumblebars::{HBData, JSONStringEscaper};
+
+    let mut parent: EvalContext = Default::default();
+    parent.register_partial("page".to_string(), parse("{{> head}}{{who}} {{#each l}}{{.}}{{/each}}").ok().unwrap());
+    parent.register_partial("head".to_string(), parse("shared ").ok().unwrap());
+    parent.register_helper("who".to_string(), Box::new(|_, _, out, _| "parent".write_value(out)));
+    parent.set_escaper(Box::new(JSONStringEscaper));
+    let parent = Rc::new(parent);
+
+    let mut child = EvalContext::child(parent.clone());
+    child.register_helper("who".to_string(), Box::new(|_, _, out, _| "child\"".write_value(out)));
+    child.register_partial("head".to_string(), parse("own ").ok().unwrap());
+
+    let json = Json::from_str(r##"{"l": [1, 2]}"##).ok().unwrap();
+    assert_eq!(parent.render_to_string("page", &json).unwrap(), "shared parent 12");
+    assert_eq!(child.render_to_string("page", &json).unwrap(), "own child\\\" 12");
+    assert!(child.partials().get("page").is_none());
+  }
+
 }
 
 /// stream testing, run with :
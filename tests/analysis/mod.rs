@@ -0,0 +1,66 @@
+use rumblebars::{parse, EvalContext, Analysis, PathUsage};
+
+fn paths(analysis: &Analysis) -> Vec<String> {
+  analysis.paths.iter().map(|p| p.to_string()).collect()
+}
+
+fn resolved(analysis: &Analysis) -> Vec<String> {
+  analysis.paths.iter().map(PathUsage::resolved).map(|p| p.to_string()).collect()
+}
+
+#[test]
+fn scopes() {
+  let template = parse("{{#each posts}}{{#if draft}}{{title}}{{else}}{{../site}}{{/if}}{{#comments}}{{@index}} {{text}} {{../../@root.site}}{{/comments}}{{^tags}}{{none}}{{/tags}}{{/each}}").unwrap();
+  let analysis = template.analyze(&EvalContext::new());
+
+  assert_eq!(paths(&analysis), vec![
+    "../../@root.site in each posts, comments",
+    "../site in each posts",
+    "comments in each posts",
+    "draft in each posts",
+    "none in each posts",
+    "posts",
+    "tags in each posts",
+    "text in each posts, comments",
+    "title in each posts",
+  ]);
+  assert_eq!(resolved(&analysis), vec![
+    "site", "site", "comments in each posts", "draft in each posts", "none in each posts",
+    "posts", "tags in each posts", "text in each posts, comments", "title in each posts",
+  ]);
+}
+
+#[test]
+fn helpers() {
+  let mut context = EvalContext::new();
+  context.register_helper("now".to_string(), Box::new(|_, _, _, _| Ok(())));
+
+  let template = parse("{{now}} {{format date pattern=\"iso\"}} {{#unless hidden}}{{name}}{{/unless}}").unwrap();
+  let analysis = template.analyze(&context);
+
+  assert_eq!(analysis.helpers.iter().collect::<Vec<_>>(), vec!["format", "now", "unless"]);
+  assert_eq!(paths(&analysis), vec!["date", "hidden", "name"]);
+}
+
+#[test]
+fn partials() {
+  let mut context = EvalContext::new();
+  context.register_partial("page".to_string(), parse("{{> shared/header}}{{#each items}}{{> item .}}{{/each}}").unwrap());
+  context.register_partial("shared/header".to_string(), parse("<h1>{{title}}</h1>").unwrap());
+  context.register_partial("item".to_string(), parse("{{name}}{{#each children}}{{> item .}}{{/each}}{{> footer}}").unwrap());
+
+  let analysis = context.analyze("page").unwrap();
+
+  assert_eq!(analysis.partials.iter().collect::<Vec<_>>(), vec!["footer", "item", "shared/header"]);
+  assert_eq!(analysis.missing_partials.iter().collect::<Vec<_>>(), vec!["footer"]);
+  assert_eq!(paths(&analysis), vec![
+    ". in each items",
+    ". in each items, > item ., each children",
+    "children in each items, > item .",
+    "items",
+    "name in each items, > item .",
+    "title",
+  ]);
+
+  assert!(context.analyze("missing").is_none());
+}
@@ -0,0 +1,30 @@
+use std::path::Path;
+
+use rumblebars::{parse, parse_contextual, encode_templates, decode_templates, CacheError, Template, TemplateLoader, CACHE_VERSION};
+
+fn debug(templates: &[(String, Template)]) -> Vec<(String, String)> {
+  templates.iter().map(|&(ref name, ref t)| (name.clone(), format!("{:?}", t.entries))).collect()
+}
+
+#[test]
+fn round_trip() {
+  let mut templates = TemplateLoader::new().load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/loader/templates")).unwrap();
+  templates.push(("helpers".to_string(), parse("{{#if a}}\n  {{> p x}}\n{{else}}{{~fmt \"s\" 1 2.5 true key=v.[0]~}}{{/if}}{{^b}}{{{c}}}{{/b}}").unwrap()));
+  templates.push(("contextual".to_string(), parse_contextual("<a href=\"{{url}}\" onclick=\"f('{{id}}')\">{{name}}</a>").unwrap()));
+
+  let cache = encode_templates(&templates).unwrap();
+  let loaded = decode_templates(&cache).unwrap();
+
+  assert_eq!(debug(&loaded), debug(&templates));
+}
+
+#[test]
+fn stale_cache() {
+  let templates = vec![("t".to_string(), parse("{{a}}").unwrap())];
+  let cache = encode_templates(&templates).unwrap();
+  let stale = cache.replace(&format!("\"version\":{}", CACHE_VERSION), "\"version\":0");
+
+  assert!(match decode_templates(&stale) { Err(CacheError::Version(Some(0))) => true, _ => false });
+  assert!(match decode_templates("{\"templates\":[]}") { Err(CacheError::Version(None)) => true, _ => false });
+  assert!(match decode_templates("{") { Err(CacheError::Syntax(_)) => true, _ => false });
+}
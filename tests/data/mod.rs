@@ -0,0 +1,186 @@
+use std::collections::{HashMap, BTreeMap};
+use std::rc::Rc;
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+
+use rumblebars::Template;
+use rumblebars::HBData;
+use rumblebars::EvalContext;
+use rumblebars::SafeWriting;
+use rumblebars::SafeString;
+use rumblebars::LazyData;
+use rumblebars::LazyMap;
+use rumblebars::Layers;
+use serialize::json::Json;
+
+fn render(template: &str, data: &HBData) -> String {
+  Template::new(template).unwrap().eval_to_string(data).unwrap()
+}
+
+#[test]
+fn scalars() {
+  assert_eq!(render("{{.}}", &42u32), "42");
+  assert_eq!(render("{{.}}", &-3i64), "-3");
+  assert_eq!(render("{{.}}", &1.5f64), "1.5");
+  assert_eq!(render("{{.}}", &true), "true");
+  assert_eq!(render("{{.}}", &()), "");
+}
+
+#[test]
+fn scalars_truthiness() {
+  let tmpl = "{{#if .}}yes{{else}}no{{/if}}";
+
+  assert_eq!(render(tmpl, &0i32), "no");
+  assert_eq!(render(tmpl, &7u8), "yes");
+  assert_eq!(render(tmpl, &0.0f32), "no");
+  assert_eq!(render(tmpl, &::std::f64::NAN), "no");
+  assert_eq!(render(tmpl, &false), "no");
+  assert_eq!(render(tmpl, &None::<String>), "no");
+  assert_eq!(render(tmpl, &Some("".to_string())), "no");
+  assert_eq!(render(tmpl, &Some(1u8)), "yes");
+  assert_eq!(render(tmpl, &Vec::<u8>::new()), "no");
+  assert_eq!(render(tmpl, &HashMap::<String, u8>::new()), "yes");
+}
+
+#[test]
+fn collections() {
+  let v = vec![1, 2, 3];
+
+  assert_eq!(render("{{.}}", &v), "1,2,3");
+  assert_eq!(render("{{#each .}}[{{.}}]{{/each}}", &v), "[1][2][3]");
+  assert_eq!(render("{{[1]}}", &&v[..]), "2");
+}
+
+#[test]
+fn maps() {
+  let mut hash = HashMap::new();
+  hash.insert("hello".to_string(), "hi".to_string());
+
+  let mut btree = BTreeMap::new();
+  btree.insert("a".to_string(), 1);
+  btree.insert("b".to_string(), 2);
+
+  assert_eq!(render("{{hello}}", &hash), "hi");
+  assert_eq!(render("{{#each .}}{{@key}}:{{.}} {{/each}}", &btree), "a:1 b:2 ");
+}
+
+#[test]
+fn pointers_and_tuples() {
+  let mut mixed: BTreeMap<String, Box<HBData>> = BTreeMap::new();
+  mixed.insert("name".to_string(), Box::new("rumble".to_string()));
+  mixed.insert("count".to_string(), Box::new(Rc::new(3u8)));
+  mixed.insert("pair".to_string(), Box::new(("left", 2.5f32)));
+
+  assert_eq!(render("{{name}} {{count}} {{pair}} {{pair.[0]}}", &mixed), "rumble 3 left,2.5 left");
+}
+
+#[test]
+fn lazy_computed_once() {
+  let calls = Rc::new(Cell::new(0));
+  let counter = calls.clone();
+  let lazy = LazyData::new(move || {
+    counter.set(counter.get() + 1);
+    vec!["a".to_string(), "b".to_string()]
+  });
+
+  assert_eq!(calls.get(), 0);
+  assert_eq!(render("{{.}} {{[1]}} {{#each .}}{{.}}{{/each}}", &lazy), "a,b b ab");
+  assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn lazy_retried_after_panic() {
+  let calls = Rc::new(Cell::new(0));
+  let counter = calls.clone();
+  let lazy = LazyData::new(move || {
+    counter.set(counter.get() + 1);
+    if counter.get() == 1 {
+      panic!("first computation fails");
+    }
+    "computed".to_string()
+  });
+
+  assert!(panic::catch_unwind(AssertUnwindSafe(|| lazy.get().clone())).is_err());
+  assert!(!lazy.is_computed());
+
+  assert_eq!(render("{{.}}", &lazy), "computed");
+  assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn lazy_map_untouched_entries() {
+  let calls = Rc::new(Cell::new(0));
+  let mut data = LazyMap::new();
+
+  data.insert("name".to_string(), "rumble".to_string());
+
+  let counter = calls.clone();
+  data.insert_lazy("expensive".to_string(), move || { counter.set(counter.get() + 1); 42 });
+
+  assert_eq!(render("{{name}}", &data), "rumble");
+  assert_eq!(calls.get(), 0);
+
+  assert_eq!(render("{{name}} {{expensive}} {{expensive}}", &data), "rumble 42 42");
+  assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn layers_lookup_order() {
+  let site = Json::from_str(r##"{"title": "site", "lang": "en", "nav": {"home": "/"}}"##).unwrap();
+  let page = Json::from_str(r##"{"title": "page", "body": "content"}"##).unwrap();
+  let mut request = HashMap::new();
+  request.insert("user".to_string(), "nico".to_string());
+
+  let data = Layers::new().layer(&request).layer(&page).layer(&site);
+
+  assert_eq!(render("{{title}} {{lang}} {{body}} {{user}} {{nav.home}}", &data), "page en content nico /");
+}
+
+#[test]
+fn layers_merged_iteration() {
+  let base = Json::from_str(r##"{"a": 1, "b": 2}"##).unwrap();
+  let over = Json::from_str(r##"{"b": 3, "c": 4}"##).unwrap();
+
+  let data = Layers::new().layer(&over).layer(&base);
+
+  assert_eq!(data.keys().collect::<Vec<_>>(), vec!["b", "c", "a"]);
+  assert_eq!(render("{{#each .}}{{@key}}={{.}} {{/each}}", &data), "b=3 c=4 a=1 ");
+}
+
+#[test]
+fn layers_owned() {
+  let page = Json::from_str(r##"{"title": "page"}"##).unwrap();
+  let mut defaults = HashMap::new();
+  defaults.insert("title".to_string(), "default".to_string());
+  defaults.insert("lang".to_string(), "en".to_string());
+
+  let data = Layers::new().layer(&page).owned_layer(defaults);
+
+  assert_eq!(render("{{title}} {{lang}}", &data), "page en");
+}
+
+#[test]
+fn safe_string() {
+  let mut data = BTreeMap::new();
+  data.insert("safe".to_string(), Box::new(SafeString::new("<b>")) as Box<HBData>);
+  data.insert("unsafe".to_string(), Box::new("<b>".to_string()) as Box<HBData>);
+
+  assert_eq!(render("{{safe}} {{unsafe}} {{{unsafe}}}", &data), "<b> &lt;b&gt; <b>");
+}
+
+#[test]
+fn safe_string_from_helper() {
+  let mut context = EvalContext::new();
+  let mut buf = Vec::new();
+
+  context.register_helper("tag".to_string(), Box::new(|params, _, out, _| {
+    let mut name = Vec::new();
+    try!(params[0].write_value(&mut SafeWriting::Unsafe(&mut name)));
+    SafeString::new(format!("<{}>\n</{}>", String::from_utf8_lossy(&name), String::from_utf8_lossy(&name))).write_value(out)
+  }));
+  context.register_partial("indented".to_string(), Template::new("{{tag \"p\"}}").unwrap());
+
+  Template::new("{{tag \"b\"}}\n  {{> indented}}\nend").unwrap().eval(&(), &mut buf, &context).unwrap();
+
+  assert_eq!(String::from_utf8(buf).unwrap(), "<b>\n</b>\n  <p>\n  </p>end");
+}
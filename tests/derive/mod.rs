@@ -0,0 +1,122 @@
+use serialize::json::Json;
+
+use rumblebars::Template;
+use rumblebars::HBData;
+use rumblebars::Lambda;
+
+#[derive(HBData)]
+struct Author {
+  name: String,
+  #[hbdata(rename = "e-mail")]
+  email: String,
+  #[hbdata(skip)]
+  #[allow(dead_code)]
+  password: String,
+}
+
+#[derive(HBData)]
+struct Post {
+  title: String,
+  author: Author,
+  tags: Json,
+  kind: Kind,
+}
+
+#[derive(HBData)]
+enum Kind {
+  Draft,
+  #[hbdata(rename = "published-post")]
+  Published,
+  Scheduled(String),
+  Guest { by: String },
+}
+
+#[derive(HBData)]
+enum Label {
+  Text(String),
+  Computed(Lambda),
+}
+
+#[derive(HBData)]
+struct Greeting {
+  name: String,
+  label: Label,
+}
+
+#[derive(HBData)]
+struct Slug(String);
+
+#[derive(HBData)]
+struct Pair(String, String);
+
+fn author() -> Author {
+  Author {
+    name: "Nicolas".to_string(),
+    email: "nico@example.org".to_string(),
+    password: "secret".to_string(),
+  }
+}
+
+fn post(kind: Kind) -> Post {
+  Post {
+    title: "Rumble".to_string(),
+    author: author(),
+    tags: Json::from_str(r##"["rust", "handlebars"]"##).unwrap(),
+    kind: kind,
+  }
+}
+
+#[test]
+fn derived_struct_keys() {
+  let a = author();
+
+  assert_eq!(a.keys().collect::<Vec<_>>(), vec!["name", "e-mail"]);
+  assert!(a.get_key("password").is_none());
+  assert!(a.get_key("email").is_none());
+  assert_eq!(a.iter().count(), a.values().count());
+}
+
+#[test]
+fn derived_struct_render() {
+  let tmpl = Template::new("{{title}} by {{author.name}} <{{author.[e-mail]}}>{{author.password}}").unwrap();
+
+  assert_eq!(tmpl.eval_to_string(&post(Kind::Draft)).unwrap(), "Rumble by Nicolas <nico@example.org>");
+}
+
+#[test]
+fn derived_struct_each() {
+  let tmpl = Template::new("{{#with author}}{{#each .}}{{@key}}={{.}} {{/each}}{{/with}}{{#each tags}}{{.}},{{/each}}").unwrap();
+
+  assert_eq!(tmpl.eval_to_string(&post(Kind::Draft)).unwrap(), "name=Nicolas e-mail=nico@example.org rust,handlebars,");
+}
+
+#[test]
+fn derived_enum() {
+  let tmpl = Template::new("{{kind}}{{kind.by}}").unwrap();
+
+  assert_eq!(tmpl.eval_to_string(&post(Kind::Draft)).unwrap(), "Draft");
+  assert_eq!(tmpl.eval_to_string(&post(Kind::Published)).unwrap(), "published-post");
+  assert_eq!(tmpl.eval_to_string(&post(Kind::Scheduled("tomorrow".to_string()))).unwrap(), "tomorrow");
+  assert_eq!(tmpl.eval_to_string(&post(Kind::Guest { by: "someone".to_string() })).unwrap(), "someone");
+}
+
+#[test]
+fn derived_tuple_structs() {
+  let slug = Slug("a-slug".to_string());
+  let pair = Pair("left".to_string(), "right".to_string());
+
+  assert_eq!(Template::new("{{.}}").unwrap().eval_to_string(&slug).unwrap(), "a-slug");
+  assert_eq!(Template::new("{{.}} {{[1]}} {{#each .}}({{.}}){{/each}}").unwrap().eval_to_string(&pair).unwrap(), "left,right right (left)(right)");
+}
+
+#[test]
+fn derived_enum_lambda() {
+  let text = Greeting { name: "nico".to_string(), label: Label::Text("plain".to_string()) };
+  let computed = Greeting {
+    name: "nico".to_string(),
+    label: Label::Computed(Lambda::new(|section, render| render(section.unwrap_or("{{name}}")).map(|s| s.to_uppercase()))),
+  };
+
+  assert_eq!(Template::new("{{label}}").unwrap().eval_to_string(&text).unwrap(), "plain");
+  assert_eq!(Template::new("{{label}}|{{#label}}hi {{name}}{{/label}}").unwrap().eval_to_string(&computed).unwrap(), "NICO|HI NICO");
+}
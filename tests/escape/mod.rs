@@ -0,0 +1,143 @@
+use std::io::Write;
+
+use serialize::json::Json;
+
+use rumblebars::{Template, EvalContext, Escaper, EscapeWriter, SafeWriting};
+use rumblebars::{HTMLEscaper, JSONStringEscaper, JavascriptEscaper, URLEscaper, CSVEscaper, LatexEscaper, MarkdownEscaper, NoEscaper};
+
+fn render_with(escaper: Box<Escaper>, template: &str, json: &str) -> String {
+  let mut context = EvalContext::new();
+  let mut buf = Vec::new();
+  context.set_escaper(escaper);
+
+  Template::new(template).unwrap().eval(&Json::from_str(json).unwrap(), &mut buf, &context).unwrap();
+
+  String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn default_html() {
+  assert_eq!(render_with(Box::new(HTMLEscaper), "{{v}}", r##"{"v": "<a href='x'>&</a>"}"##), "&lt;a href=&#x27;x&#x27;&gt;&amp;&lt;/a&gt;");
+}
+
+#[test]
+fn json_string() {
+  assert_eq!(render_with(Box::new(JSONStringEscaper), r##"{"v": "{{v}}"}"##, r##"{"v": "say \"hi\"\n\\ \u0001"}"##), r##"{"v": "say \"hi\"\n\\ \u0001"}"##);
+}
+
+#[test]
+fn javascript() {
+  assert_eq!(render_with(Box::new(JavascriptEscaper), "var v = '{{v}}';", r##"{"v": "it's </script>"}"##), r##"var v = 'it\'s \u003c/script\u003e';"##);
+}
+
+#[test]
+fn url() {
+  assert_eq!(render_with(Box::new(URLEscaper), "/search?q={{q}}", r##"{"q": "a b&c=é"}"##), "/search?q=a%20b%26c%3D%C3%A9");
+}
+
+#[test]
+fn csv() {
+  assert_eq!(render_with(Box::new(CSVEscaper), r##""{{a}}","{{b}}""##, r##"{"a": "say \"hi\"", "b": "x,y"}"##), r##""say ""hi""","x,y""##);
+}
+
+#[test]
+fn latex() {
+  assert_eq!(render_with(Box::new(LatexEscaper), "{{v}}", r##"{"v": "50% of $x_1 \\ ~"}"##), "50\\% of \\$x\\_1 \\textbackslash{} \\textasciitilde{}");
+}
+
+#[test]
+fn markdown() {
+  assert_eq!(render_with(Box::new(MarkdownEscaper), "**{{v}}**", r##"{"v": "*not* [a](link)"}"##), "**\\*not\\* \\[a\\]\\(link\\)**");
+}
+
+#[test]
+fn no_escape() {
+  assert_eq!(render_with(Box::new(NoEscaper), "{{v}}", r##"{"v": "<&>"}"##), "<&>");
+}
+
+#[test]
+fn triple_stash_bypasses_escaper() {
+  assert_eq!(render_with(Box::new(URLEscaper), "{{{v}}} {{v}}", r##"{"v": "a b"}"##), "a b a%20b");
+}
+
+#[test]
+fn split_characters() {
+  let mut buf = Vec::new();
+  {
+    let mut writer = EscapeWriter::new(&mut buf, &HTMLEscaper);
+    let bytes = "é<".as_bytes();
+    writer.write_all(&bytes[..1]).unwrap();
+    writer.write_all(&bytes[1..]).unwrap();
+  }
+
+  assert_eq!(String::from_utf8(buf).unwrap(), "é&lt;");
+}
+
+#[test]
+fn incomplete_character_at_end() {
+  let mut buf = Vec::new();
+  let mut writer = EscapeWriter::new(&mut buf, &HTMLEscaper);
+  writer.write_all(&"é".as_bytes()[..1]).unwrap();
+
+  assert!(writer.finish().is_err());
+}
+
+#[test]
+fn unescaped_writes_keep_order() {
+  let mut buf = Vec::new();
+  {
+    let mut writer = EscapeWriter::new(&mut buf, &HTMLEscaper);
+    {
+      let mut out = SafeWriting::Safe(&mut writer);
+      let bytes = "<é".as_bytes();
+      out.write_all(&bytes[..2]).unwrap();
+      out.into_unsafe().write_all(&bytes[2..]).unwrap();
+      out.into_unsafe().write_all(b"<").unwrap();
+    }
+    writer.finish().unwrap();
+  }
+
+  assert_eq!(String::from_utf8(buf).unwrap(), "&lt;é<");
+}
+
+fn render_contextual(template: &str, json: &str) -> String {
+  ::rumblebars::parse_contextual(template).unwrap().eval_to_string(&Json::from_str(json).unwrap()).unwrap()
+}
+
+#[test]
+fn contextual_text_and_attributes() {
+  let data = r##"{"v": "a\"b <c>"}"##;
+
+  assert_eq!(render_contextual("<p title=\"{{v}}\">{{v}}</p>", data), "<p title=\"a&quot;b &lt;c&gt;\">a&quot;b &lt;c&gt;</p>");
+  assert_eq!(render_contextual("<p title={{v}}>", data), "<p title=a&quot;b&#32;&lt;c&gt;>");
+  assert_eq!(render_contextual("<p {{v}}>", data), "<p ZgotmplZ>");
+  assert_eq!(render_contextual("<!-- {{v}} -->", data), "<!--  -->");
+}
+
+#[test]
+fn contextual_urls() {
+  assert_eq!(render_contextual("<a href=\"{{u}}\">", r##"{"u": "javascript:alert(1)"}"##), "<a href=\"#ZgotmplZ\">");
+  assert_eq!(render_contextual("<a href=\"{{u}}\">", r##"{"u": "https://example.org/a b?x=1&y=2"}"##), "<a href=\"https://example.org/a%20b?x=1&amp;y=2\">");
+  assert_eq!(render_contextual("<a href=\"/search?q={{q}}\">", r##"{"q": "a&b c"}"##), "<a href=\"/search?q=a%26b%20c\">");
+}
+
+#[test]
+fn contextual_scripts() {
+  let data = r##"{"s": "</script><b>'", "n": 42}"##;
+
+  assert_eq!(render_contextual("<script>var s = {{s}}, n = {{n}};</script>", data), "<script>var s = \"\\u003c/script\\u003e\\u003cb\\u003e\\'\", n = 42;</script>");
+  assert_eq!(render_contextual("<script>var s = '{{s}}';</script>", data), "<script>var s = '\\u003c/script\\u003e\\u003cb\\u003e\\'';</script>");
+  assert_eq!(render_contextual("<button onclick=\"f({{n}})\">", data), "<button onclick=\"f(42)\">");
+}
+
+#[test]
+fn contextual_styles() {
+  assert_eq!(render_contextual("<p style=\"color: {{c}}\">", r##"{"c": "#fff"}"##), "<p style=\"color: #fff\">");
+  assert_eq!(render_contextual("<style>p { color: {{c}} }</style>", r##"{"c": "red;background:url(x)"}"##), "<style>p { color: ZgotmplZ }</style>");
+}
+
+#[test]
+fn contextual_blocks() {
+  assert_eq!(render_contextual("<ul>{{#each l}}<li class=\"{{.}}\">{{.}}</li>{{/each}}</ul>", r##"{"l": ["a", "<b>"]}"##), "<ul><li class=\"a\">a</li><li class=\"&lt;b&gt;\">&lt;b&gt;</li></ul>");
+  assert!(::rumblebars::parse_contextual("<p {{#if c}}title=\"{{/if}}\">").is_err());
+}
@@ -3,9 +3,35 @@ use std::path::Path;
 use std::fs;
 use std::io::Read;
 use std::default::Default;
+use std::cell::Cell;
+use std::collections::BTreeMap;
 
 use serialize::json::Json;
-use rumblebars::HBData;
+use rumblebars::{HBData, Lambda, Layers};
+
+// rust counterparts of ~lambdas specs code
+fn spec_lambda(test_name: &str) -> Option<Lambda> {
+  match test_name {
+    "Interpolation"              => Some(Lambda::new(|_, _| Ok("world".to_string()))),
+    "Interpolation - Expansion"  => Some(Lambda::new(|_, _| Ok("{{planet}}".to_string()))),
+    "Interpolation - Multiple Calls" => {
+      let calls = Cell::new(0);
+      Some(Lambda::new(move |_, _| { calls.set(calls.get() + 1); Ok(calls.get().to_string()) }))
+    },
+    "Escaping"                   => Some(Lambda::new(|_, _| Ok(">".to_string()))),
+    "Section"                    => Some(Lambda::new(|text, _| Ok((if text == Some("{{x}}") { "yes" } else { "no" }).to_string()))),
+    "Section - Expansion"        => Some(Lambda::new(|text, _| { let t = text.unwrap_or(""); Ok(format!("{}{{{{planet}}}}{}", t, t)) })),
+    "Section - Multiple Calls"   => Some(Lambda::new(|text, _| Ok(format!("__{}__", text.unwrap_or(""))))),
+    "Inverted Section"           => Some(Lambda::new(|_, _| Ok("".to_string()))),
+    _ => None,
+  }
+}
+
+// ~lambdas specs that change delimiters, expected failures
+const LAMBDAS_SKIPPED: [&'static str; 2] = [
+  "Interpolation - Alternate Delimiters",
+  "Section - Alternate Delimiters",
+];
 
 fn test_set(set_name: &str) {
   let path_str = format!("{}/tests/mustache/specs", option_env!("CARGO_MANIFEST_DIR").unwrap_or("."));
@@ -33,6 +59,10 @@ fn test_set(set_name: &str) {
                     return; // skip this test, expected result is mostly achieved, divert only with some dangling space
                   }
 
+                  if set_name == "~lambdas" && LAMBDAS_SKIPPED.contains(&test_name) {
+                    continue; // delimiters changes are not supported
+                  }
+
                   let tmpl = ::rumblebars::parse(template).ok().unwrap();
                   let mut buf: Vec<u8> = Vec::new();
                   let mut eval_context: ::rumblebars::EvalContext = Default::default();
@@ -47,7 +77,15 @@ fn test_set(set_name: &str) {
                     _ => (),
                   }
 
-                  ::rumblebars::eval(&tmpl, data, &mut buf, &eval_context).unwrap_or(());
+                  let lambda = if set_name == "~lambdas" { spec_lambda(test_name) } else { None };
+                  match lambda {
+                    Some(lambda) => {
+                      let mut lambda_data = BTreeMap::new();
+                      lambda_data.insert("lambda".to_string(), lambda);
+                      ::rumblebars::eval(&tmpl, &Layers::new().layer(&lambda_data).layer(data), &mut buf, &eval_context).unwrap_or(());
+                    },
+                    None => ::rumblebars::eval(&tmpl, data, &mut buf, &eval_context).unwrap_or(()),
+                  }
 
                   let result = String::from_utf8(buf).unwrap_or("<<result has invalid utf8>>".to_string());
 
@@ -93,4 +131,7 @@ mustache_tests_set!(inverted);
 mustache_tests_set!(partials);
 mustache_tests_set!(sections);
 
-
+#[test]
+fn lambdas() {
+  test_set("~lambdas");
+}
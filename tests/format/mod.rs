@@ -0,0 +1,52 @@
+use serialize::json::Json;
+
+use rumblebars::{parse, Formatter, FormatError, ParseError};
+
+fn render(template: &str, json: &str) -> String {
+  parse(template).unwrap().eval_to_string(&Json::from_str(json).unwrap()).unwrap()
+}
+
+#[test]
+fn normalized() {
+  let source = "<ul>\n{{#each  items }}\n<li>{{ name }} {{{ html}}}</li>\n    {{#if  done}}\n<li>{{> item   . }}</li>\n {{/if}}\n{{/each}}\n</ul>";
+
+  assert_eq!(Formatter::new().format(source).unwrap(),
+    "<ul>\n{{#each items}}\n<li>{{name}} {{{html}}}</li>\n  {{#if done}}\n<li>{{> item .}}</li>\n  {{/if}}\n{{/each}}\n</ul>");
+  assert_eq!(Formatter::new().indent("\t").format("{{#a}}\nx\n{{#b}}\ny\n{{/b}}\n{{/a}}").unwrap(), "{{#a}}\nx\n\t{{#b}}\ny\n\t{{/b}}\n{{/a}}\n");
+}
+
+#[test]
+fn same_output() {
+  let templates = [
+    "<ul>\n  {{#each items}}\n    <li>{{name}}</li>\n  {{^}}\n    none\n  {{/each}}\n</ul>\n",
+    "  {{#if a}} inline {{/if}}  \n{{~#if b}}\n  b {{{ c }}}\n  {{~/if}}",
+    "a\n  {{> p}}\n{{#with o}}{{.}}{{/with}}",
+    "\\{{not}} \\\\{{name}}",
+  ];
+  let data = r##"{"items": [{"name": "x"}, {"name": "<y>"}], "a": true, "b": 1, "c": "<c>", "name": "n", "o": "o"}"##;
+
+  for template in templates.iter() {
+    let formatted = Formatter::new().format(template).unwrap();
+
+    assert_eq!(render(&formatted, data), render(template, data));
+    assert_eq!(render(&formatted, "{}"), render(template, "{}"));
+    assert!(Formatter::new().is_formatted(&formatted).unwrap());
+  }
+}
+
+#[test]
+fn comments() {
+  let source = "{{!-- lint-disable triple-stash --}}\n{{#a}}\nx {{! note}}\n{{!-- nested --}}\n{{/a}}";
+
+  let formatted = Formatter::new().format(source).unwrap();
+  assert_eq!(formatted, "{{!-- lint-disable triple-stash --}}\n{{#a}}\nx {{! note}}\n  {{!-- nested --}}\n{{/a}}\n");
+  assert_eq!(render(&formatted, r#"{"a": true}"#), render(source, r#"{"a": true}"#));
+}
+
+#[test]
+fn parse_error() {
+  assert!(match Formatter::new().format("{{#a}}\n{{/b}}") {
+    Err(FormatError::Parse(ref failure)) => match failure.error { ParseError::UnmatchedBlock => failure.line == 2, _ => false },
+    _ => false,
+  });
+}
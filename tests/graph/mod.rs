@@ -0,0 +1,83 @@
+use std::rc::Rc;
+
+use rumblebars::{parse, EvalContext, PartialGraph, Cycle};
+
+fn context(templates: &[(&str, &str)]) -> EvalContext {
+  let mut context = EvalContext::new();
+  for &(name, source) in templates.iter() {
+    context.register_partial(name.to_string(), parse(source).unwrap());
+  }
+  context
+}
+
+fn cycle(templates: &[&str], guarded: bool) -> Cycle {
+  Cycle { templates: templates.iter().map(|t| t.to_string()).collect(), guarded: guarded }
+}
+
+#[test]
+fn includes() {
+  let context = context(&[
+    ("page", "{{> shared/header}}{{#if menu}}{{> menu}}{{/if}}{{> menu}}"),
+    ("shared/header", "<h1>{{title}}</h1>"),
+  ]);
+  let graph = PartialGraph::new(&context);
+
+  assert_eq!(graph.templates(), vec!["page", "shared/header"]);
+  assert_eq!(graph.includes("page"), vec!["menu", "shared/header"]);
+  assert_eq!(graph.missing(), vec![("page", "menu")]);
+  assert!(graph.cycles().is_empty());
+}
+
+#[test]
+fn cycles() {
+  let context = context(&[
+    ("a", "{{> b}}"),
+    ("b", "{{#if again}}{{> a}}{{/if}}"),
+    ("c", "{{> d}}"),
+    ("d", "{{#each items}}{{> c}}{{/each}}{{> e}}"),
+    ("e", "{{> d}}"),
+    ("tree", "{{#each children}}{{> tree}}{{/each}}"),
+  ]);
+
+  assert_eq!(PartialGraph::new(&context).cycles(), vec![
+    cycle(&["a", "b"], true),
+    cycle(&["c", "d", "e"], false),
+    cycle(&["tree"], true),
+  ]);
+
+  // `d -> e -> d` has no guard
+  let graph = PartialGraph::new(&context).allow_recursion("a").allow_recursion("d").allow_recursion("tree");
+  assert_eq!(graph.cycles(), vec![cycle(&["c", "d", "e"], false)]);
+}
+
+#[test]
+fn parent_context() {
+  let parent = Rc::new(context(&[("layout", "{{> page}}")]));
+  let mut child = EvalContext::child(parent);
+  child.register_partial("page".to_string(), parse("{{> layout}}").unwrap());
+
+  let graph = PartialGraph::new(&child);
+  assert_eq!(graph.templates(), vec!["layout", "page"]);
+  assert_eq!(graph.cycles(), vec![cycle(&["layout", "page"], false)]);
+}
+
+#[test]
+fn dot() {
+  let context = context(&[
+    ("page", "{{> header}}{{#each items}}{{> item}}{{/each}}"),
+    ("item", "{{> item}}"),
+    ("header", "{{> logo}}"),
+  ]);
+
+  assert_eq!(PartialGraph::new(&context).to_dot(), "digraph partials {
+  \"header\";
+  \"item\" [color=red];
+  \"page\";
+  \"logo\" [color=red, style=dashed];
+  \"header\" -> \"logo\";
+  \"item\" -> \"item\";
+  \"page\" -> \"header\";
+  \"page\" -> \"item\" [style=dashed];
+}
+");
+}
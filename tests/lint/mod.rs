@@ -0,0 +1,92 @@
+use serialize::json::ToJson;
+
+use rumblebars::{parse, EvalContext, Linter, LintRule, Severity};
+
+const SOURCE: &'static str = "{{#if a}} {{/if}}{{#each items}}{{../../../x}}{{/each}}{{link url class=\"a\" class=\"b\"}}{{&raw}}{{> missing}}";
+
+fn lint(linter: &Linter, source: &str) -> Vec<String> {
+  linter.lint("t", source, &EvalContext::new()).unwrap().iter().map(|lint| lint.to_string()).collect()
+}
+
+#[test]
+fn rules() {
+  assert_eq!(lint(&Linter::new(), SOURCE), vec![
+    "t:1:1: warning[empty-block]: block `if` is empty",
+    "t:1:33: warning[deep-path]: `../../../x` goes up 3 parent contexts",
+    "t:1:56: error[unknown-helper]: helper `link` is not registered",
+    "t:1:56: error[duplicate-hash-key]: option `class` is given twice to `link`",
+    "t:1:88: warning[triple-stash]: `raw` is not html escaped",
+    "t:1:96: error[missing-partial]: partial `missing` is not registered",
+  ]);
+
+  let mut context = EvalContext::new();
+  context.register_helper("link".to_string(), Box::new(|_, _, _, _| Ok(())));
+  context.register_partial("missing".to_string(), parse("").unwrap());
+  let lints = Linter::new().max_parent_depth(3).lint("t", "{{#each items}}{{../../../x}}{{link url}}{{> missing}}{{/each}}", &context).unwrap();
+  assert!(lints.is_empty());
+}
+
+#[test]
+fn severities() {
+  let linter = Linter::new()
+    .severity(LintRule::TripleStash, Severity::Error)
+    .severity(LintRule::EmptyBlock, Severity::Allow)
+    .severity(LintRule::UnknownHelper, Severity::Warning);
+
+  assert_eq!(lint(&linter, SOURCE), vec![
+    "t:1:33: warning[deep-path]: `../../../x` goes up 3 parent contexts",
+    "t:1:56: warning[unknown-helper]: helper `link` is not registered",
+    "t:1:56: error[duplicate-hash-key]: option `class` is given twice to `link`",
+    "t:1:88: error[triple-stash]: `raw` is not html escaped",
+    "t:1:96: error[missing-partial]: partial `missing` is not registered",
+  ]);
+  assert_eq!(LintRule::from_name("duplicate-hash-key"), Some(LintRule::DuplicateHashKey));
+  assert_eq!(LintRule::from_name("unknown-rule"), Some(LintRule::UnknownRule));
+}
+
+#[test]
+fn disable_comments() {
+  assert_eq!(lint(&Linter::new(), &format!("{{{{!-- lint-disable deep-path empty-block --}}}}\n{}{{{{! lint-disable triple-stash}}}}", SOURCE)), vec![
+    "t:2:56: error[unknown-helper]: helper `link` is not registered",
+    "t:2:56: error[duplicate-hash-key]: option `class` is given twice to `link`",
+    "t:2:96: error[missing-partial]: partial `missing` is not registered",
+  ]);
+  assert!(lint(&Linter::new(), &format!("{{{{! lint-disable }}}}{}", SOURCE)).is_empty());
+
+  // a misspelled rule is reported and disables nothing else
+  assert_eq!(lint(&Linter::new(), "{{{a}}}\n{{!-- lint-disable tripple-stash --}}"), vec![
+    "t:1:1: warning[triple-stash]: `a` is not html escaped",
+    "t:2:1: warning[unknown-rule]: `tripple-stash` is not a lint rule",
+  ]);
+}
+
+#[test]
+fn json_output() {
+  let lints = Linter::new().lint("t", "{{{raw}}}", &EvalContext::new()).unwrap();
+
+  assert_eq!(lints.to_json().to_string(), r##"[{"column":1,"line":1,"message":"`raw` is not html escaped","rule":"triple-stash","severity":"warning","template":"t"}]"##);
+}
+
+#[test]
+fn unused_partials() {
+  let mut context = EvalContext::new();
+  context.register_partial("page".to_string(), parse("{{> header}}").unwrap());
+  context.register_partial("header".to_string(), parse("{{#if a}}{{> nav}}{{/if}}").unwrap());
+  context.register_partial("nav".to_string(), parse("").unwrap());
+  context.register_partial("orphan".to_string(), parse("").unwrap());
+
+  let lints: Vec<_> = Linter::new().unused_partials(&["page"], &context).iter().map(|lint| lint.to_string()).collect();
+  assert_eq!(lints, vec!["orphan: warning[unused-partial]: partial `orphan` is never included"]);
+}
+
+#[test]
+fn parse_errors() {
+  let templates = vec![
+    ("ok".to_string(), "{{x}}".to_string()),
+    ("bad".to_string(), "{{#a}}\n{{/b}}".to_string()),
+  ];
+  let errors = Linter::new().lint_templates(&templates, &EvalContext::new()).err().unwrap();
+
+  assert_eq!(errors.len(), 1);
+  assert!(errors[0].to_string().starts_with("bad:2:"));
+}
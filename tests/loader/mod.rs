@@ -0,0 +1,151 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serialize::json::Json;
+
+use rumblebars::{Template, EvalContext, TemplateLoader, FileRegistry, LoadErrorKind, ParseError, to_source};
+
+fn fixture(dir: &str) -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/loader").join(dir)
+}
+
+#[test]
+fn load_names() {
+  let templates = TemplateLoader::new().load(&fixture("templates")).unwrap();
+  let names: Vec<&str> = templates.iter().map(|&(ref name, _)| &name[..]).collect();
+
+  assert_eq!(names, vec!["index", "shared/footer", "shared/header"]);
+}
+
+#[test]
+fn load_sources() {
+  let sources = TemplateLoader::new().load_sources(&fixture("templates")).unwrap();
+
+  assert_eq!(sources.iter().map(|&(ref name, _)| &name[..]).collect::<Vec<_>>(), vec!["index", "shared/footer", "shared/header"]);
+  assert_eq!(sources[0].1, "{{> shared/header}}<p>{{content}}</p>{{> shared/footer}}");
+}
+
+#[test]
+fn load_extension() {
+  let templates = TemplateLoader::new().extension("txt").load(&fixture("templates"));
+
+  assert!(match templates {
+    Err(ref errors) => errors.len() == 1 && errors[0].path.ends_with("notes.txt"),
+    _ => false,
+  });
+}
+
+#[test]
+fn register_partials() {
+  let json = Json::from_str(r##"{"title": "T", "content": "C", "links": ["a", "b"]}"##).unwrap();
+  let mut context = EvalContext::new();
+  let mut buf = Vec::new();
+
+  TemplateLoader::new().register_partials(&fixture("templates"), &mut context).unwrap();
+  Template::new("{{> index}}").unwrap().eval(&json, &mut buf, &context).unwrap();
+
+  assert_eq!(String::from_utf8(buf).unwrap(), "<h1>T</h1><p>C</p><footer>a b </footer>");
+}
+
+#[test]
+fn dotted_partial_names() {
+  let mut context = EvalContext::new();
+  context.register_partial("a/b".to_string(), Template::new("slash").unwrap());
+  context.register_partial("a.b".to_string(), Template::new("dot").unwrap());
+
+  let template = Template::new("{{> a.b}} {{> a/b}}").unwrap();
+  let mut buf = Vec::new();
+  template.eval(&Json::Null, &mut buf, &context).unwrap();
+
+  assert_eq!(String::from_utf8(buf).unwrap(), "dot slash");
+  assert_eq!(to_source(&template.entries), "{{> a.b}} {{> a/b}}");
+}
+
+#[test]
+fn all_errors_reported() {
+  let mut context = EvalContext::new();
+  let errors = TemplateLoader::new().register_partials(&fixture("broken"), &mut context).err().unwrap();
+
+  let positions: Vec<_> = errors.iter().map(|e| match e.kind {
+    LoadErrorKind::Parse(ref failure) => (e.path.strip_prefix(&fixture("broken")).unwrap().to_path_buf(), failure.line, failure.column),
+    LoadErrorKind::Io(ref e) => panic!("unexpected io error {}", e),
+    LoadErrorKind::Precompile(ref e) => panic!("unexpected precompile error {}", e),
+  }).collect();
+
+  assert_eq!(positions, vec![
+    (PathBuf::from("mismatch.hbs"), 2, 3),
+    (PathBuf::from("nested/close.hbs"), 2, 20),
+  ]);
+  assert!(match errors[0].kind { LoadErrorKind::Parse(ref f) => match f.error { ParseError::UnmatchedBlock => true, _ => false }, _ => false });
+  assert!(errors[0].to_string().ends_with("mismatch.hbs:2:3: UnmatchedBlock, ‘unless’ does not match ‘if’"));
+
+  assert!(context.partial_with_name("ok").is_none());
+}
+
+#[test]
+fn missing_directory() {
+  let errors = TemplateLoader::new().load(&fixture("missing")).err().unwrap();
+
+  assert_eq!(errors.len(), 1);
+  assert!(match errors[0].kind { LoadErrorKind::Io(_) => true, _ => false });
+}
+
+fn write_file(path: &Path, content: &str) {
+  fs::create_dir_all(path.parent().unwrap()).unwrap();
+  fs::File::create(path).unwrap().write_all(content.as_bytes()).unwrap();
+}
+
+fn render(registry: &mut FileRegistry, name: &str) -> String {
+  let mut buf = Vec::new();
+  registry.render(name, &Json::from_str(r##"{"name": "you"}"##).unwrap(), &mut buf).unwrap();
+  String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn hot_reload() {
+  let dir = ::std::env::temp_dir().join("rumblebars-hot-reload");
+  let _ = fs::remove_dir_all(&dir);
+  write_file(&dir.join("page.hbs"), "{{> shared/greet}}!");
+  write_file(&dir.join("shared/greet.hbs"), "hi {{name}}");
+
+  let mut registry = FileRegistry::new(&dir, TemplateLoader::new(), EvalContext::new()).hot_reload(true);
+  assert_eq!(render(&mut registry, "page"), "hi you!");
+
+  // changed partial is reparsed
+  write_file(&dir.join("shared/greet.hbs"), "hello {{name}}");
+  assert_eq!(render(&mut registry, "page"), "hello you!");
+
+  // broken source keeps the previous version
+  write_file(&dir.join("shared/greet.hbs"), "{{#if name}}hey{{/unless}}");
+  assert_eq!(render(&mut registry, "page"), "hello you!");
+  assert_eq!(registry.errors().len(), 1);
+  assert!(registry.errors()[0].path.ends_with("shared/greet.hbs"));
+
+  write_file(&dir.join("shared/greet.hbs"), "hey {{name}}");
+  assert_eq!(render(&mut registry, "page"), "hey you!");
+  assert!(registry.errors().is_empty());
+
+  // removed files are dropped
+  fs::remove_file(dir.join("page.hbs")).unwrap();
+  let mut buf = Vec::new();
+  assert!(registry.render("page", &Json::Null, &mut buf).is_err());
+
+  fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn no_hot_reload() {
+  let dir = ::std::env::temp_dir().join("rumblebars-no-hot-reload");
+  let _ = fs::remove_dir_all(&dir);
+  write_file(&dir.join("page.hbs"), "hi {{name}}");
+
+  let mut registry = FileRegistry::new(&dir, TemplateLoader::new(), EvalContext::new());
+  write_file(&dir.join("page.hbs"), "hello {{name}}");
+  assert_eq!(render(&mut registry, "page"), "hi you");
+
+  assert!(registry.refresh());
+  assert_eq!(render(&mut registry, "page"), "hello you");
+
+  fs::remove_dir_all(&dir).unwrap();
+}
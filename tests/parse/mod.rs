@@ -3,6 +3,7 @@ extern crate rumblebars;
 use rumblebars::Template;
 use rumblebars::ParseError;
 use rumblebars::parse;
+use rumblebars::{to_source, Entries, HBEntry};
 
 static BIG: &'static str = r##"
     conten content
@@ -70,3 +71,80 @@ fn fail_block() {
 fn fail_nested_block() {
   assert!(match parse("{{#o}}{{/i}}{{/o}}") { Err((ParseError::UnmatchedBlock, _)) => true, Err(_) => false, Ok(_) => false })
 }
+
+// syntax tree without block sources and positions, that depend on the exact text
+fn tree(template: &str) -> String {
+  fn clear_sources(entries: &mut Entries) {
+    for entry in entries.iter_mut() {
+      match **entry {
+        HBEntry::Eval(ref mut exp) => {
+          exp.block_source = None;
+          exp.position = Default::default();
+          for block in exp.block.iter_mut().chain(exp.else_block.iter_mut()) {
+            clear_sources(block);
+          }
+        },
+        HBEntry::Partial(ref mut exp) => exp.position = Default::default(),
+        HBEntry::Comment(ref mut comment) => comment.position = Default::default(),
+        HBEntry::Raw(_) => (),
+      }
+    }
+  }
+
+  let mut entries = parse(template).unwrap().entries;
+  clear_sources(&mut entries);
+  format!("{:?}", entries)
+}
+
+#[test]
+fn source_round_trip() {
+  let templates = [
+    BIG,
+    "<ul>\n  {{#each items}}\n  <li>{{{~name~}}}</li>\n  {{else}}\n  <li>none</li>\n  {{/each}}\n</ul>\n",
+    "{{^empty}}a{{/empty}} {{#a.[b c]}}{{../x}} {{this.y}} {{@root.z}}{{/a.[b c]}}",
+    "<div>\n  {{> shared/item item size=2 label=\"a \\\"b\\\"\"}}\n</div>",
+    "a \\{{b}} c\\\\{{d}} \\e",
+    "{{#if a}}\n\n  b\n{{/if}}",
+    "{{~#if a~}}  b  {{~else~}} c {{~/if~}}",
+    "{{helper 1 -2.5 true null \"s\" key=value}}",
+    r"a\\{b \\\\\y \\\{{x}} \\\",
+  ];
+
+  for template in templates.iter() {
+    let source = parse(template).unwrap().to_source();
+
+    assert_eq!(tree(&source), tree(template));
+    assert_eq!(parse(&source).unwrap().to_source(), source);
+  }
+
+  // backslashes before braces, backslashes and text
+  for raw in [r"a\{b", r"\\\a", r"a\\\", r"\\\{{b", r"\{{", r"a\b"].iter() {
+    let source = to_source(&vec![Box::new(HBEntry::Raw(raw.to_string()))]);
+    assert_eq!(tree(&source), format!("{:?}", vec![Box::new(HBEntry::Raw(raw.to_string()))]), "{}", source);
+  }
+}
+
+#[test]
+fn source_layout() {
+  let template = "<ul>\n  {{#each items}}\n  <li>{{name}}</li>\n  {{/each}}\n</ul>";
+  assert_eq!(to_source(&parse(template).unwrap().entries), template);
+
+  // comments are kept as written, on their own line when they start one
+  assert_eq!(parse("a{{! note }}b").unwrap().to_source(), "a{{! note }}b");
+  assert_eq!(parse("{{!-- lint-disable --}}\na {{~! trimmed}} b\n  {{! line }}\nc").unwrap().to_source(), "{{!-- lint-disable --}}\na{{~! trimmed}} b\n  {{! line }}\nc");
+}
+
+#[test]
+fn positions() {
+  let template = parse("<p>\n  {{#each items}}{{! é }} {{name}}\n  {{> item}}{{/each}}").unwrap();
+  let positions = match *template.entries[1] {
+    HBEntry::Eval(ref exp) => exp.block.as_ref().unwrap().iter().filter_map(|entry| match **entry {
+      HBEntry::Eval(ref exp) | HBEntry::Partial(ref exp) => Some((exp.position.line, exp.position.column)),
+      HBEntry::Comment(ref comment) => Some((comment.position.line, comment.position.column)),
+      HBEntry::Raw(_) => None,
+    }).collect::<Vec<_>>(),
+    _ => vec![],
+  };
+
+  assert_eq!(positions, vec![(2, 18), (2, 27), (3, 3)]);
+}
@@ -0,0 +1,17 @@
+extern crate rumblebars;
+
+use std::env;
+use std::path::Path;
+use rumblebars::{Precompiler, TemplateLoader};
+
+fn main() {
+  let out = Path::new(&env::var("OUT_DIR").unwrap()).join("templates.rs");
+  println!("cargo:rerun-if-changed=templates");
+
+  if let Err(errors) = Precompiler::new(TemplateLoader::new()).compile_dir(Path::new("templates"), &out) {
+    for error in &errors {
+      println!("cargo:warning={}", error);
+    }
+    panic!("{} templates failed to compile", errors.len());
+  }
+}
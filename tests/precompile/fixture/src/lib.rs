@@ -0,0 +1,6 @@
+//! Templates of `templates/`, precompiled by the build script, so that the
+//! tests of rumblebars compile and run the generated source.
+
+extern crate rumblebars;
+
+include!(concat!(env!("OUT_DIR"), "/templates.rs"));
@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use serialize::json::Json;
+
+use rumblebars::{parse, parse_contextual, precompile, EvalContext, TemplateLoader, LoadErrorKind, HBData, HBEvalResult};
+
+use fixture;
+
+fn interpreted(template: &str, data: &Json, context: &EvalContext) -> String {
+  let mut buf = Vec::new();
+  parse(template).unwrap().eval(data, &mut buf, context).unwrap();
+  String::from_utf8(buf).unwrap()
+}
+
+// renders with a function generated from `tests/precompile/fixture/templates`
+fn compiled(function: fn(&HBData, &mut ::std::io::Write, &EvalContext) -> HBEvalResult, data: &Json, context: &EvalContext) -> String {
+  let mut buf = Vec::new();
+  function(data, &mut buf, context).unwrap();
+  String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn sections_and_values() {
+  let template = "<ul>{{#items}}<li>{{name}}</li>{{else}}empty{{/items}}</ul>{{{raw}}}";
+  let context = EvalContext::new();
+
+  for json in &[
+    r##"{"items": [{"name": "a<"}, {"name": "b"}], "raw": "<br>"}"##,
+    r##"{"items": [], "raw": "<br>"}"##,
+    r##"{"items": {"name": "single"}}"##,
+    r##"{"items": false}"##,
+  ] {
+    let data = Json::from_str(json).unwrap();
+    assert_eq!(compiled(fixture::list, &data, &context), interpreted(template, &data, &context));
+  }
+}
+
+#[test]
+fn builtin_blocks() {
+  let template = "{{#each items}}{{@index}}:{{name}}{{#if @first}}!{{/if}}{{#unless @last}},{{/unless}}{{else}}none{{/each}}|{{#with user}}{{name}}{{else}}anonymous{{/with}}|{{^if user}}no user{{/if}}";
+  let mut replaced = EvalContext::new();
+  replaced.register_helper("if".to_string(), Box::new(|_, options, out, _| options.inverse(out)));
+  replaced.register_helper("with".to_string(), Box::new(|_, _, out, _| "with".write_value(out)));
+
+  for context in &[EvalContext::new(), replaced] {
+    for json in &[
+      r##"{"items": [{"name": "a<"}, {"name": "b"}], "user": {"name": "u"}}"##,
+      r##"{"items": [], "user": false}"##,
+      r##"{"items": {"a": {"name": "key"}}, "name": "root"}"##,
+      r##"{"items": "leaf", "name": "root"}"##,
+      r##"{}"##,
+    ] {
+      let data = Json::from_str(json).unwrap();
+      assert_eq!(compiled(fixture::blocks, &data, context), interpreted(template, &data, context));
+    }
+  }
+}
+
+#[test]
+fn helpers_fallback() {
+  let data = Json::from_str(r##"{"items": [{"name": "a"}], "name": "b"}"##).unwrap();
+  let mut context = EvalContext::new();
+  context.register_helper("name".to_string(), Box::new(|_, _, out, _| "helper".write_value(out)));
+
+  assert_eq!(compiled(fixture::list, &data, &context), "<ul><li>helper</li></ul>");
+}
+
+#[test]
+fn partials() {
+  let data = Json::from_str(r##"{"name": "root", "items": [{"name": "first"}]}"##).unwrap();
+  let mut context = EvalContext::new();
+  context.register_partial("item".to_string(), parse("{{name}}").unwrap());
+
+  assert_eq!(compiled(fixture::partial, &data, &context), "root|first");
+}
+
+#[test]
+fn function_names() {
+  let data = Json::from_str(r##"{"name": "n"}"##).unwrap();
+  let context = EvalContext::new();
+
+  assert_eq!(compiled(fixture::item, &data, &context), "n");
+  assert_eq!(compiled(fixture::item_body_1, &data, &context), "body of n");
+  assert_eq!(compiled(fixture::render_, &data, &context), "render n");
+  assert_eq!(compiled(fixture::type_, &data, &context), "type n");
+  assert_eq!(compiled(fixture::match_, &data, &context), "match n");
+
+  let mut buf = Vec::new();
+  fixture::render("item_body", &data, &mut buf, &context).unwrap();
+  assert_eq!(String::from_utf8(buf).unwrap(), "body of n");
+  assert!(fixture::render("missing", &data, &mut Vec::new(), &context).is_err());
+}
+
+#[test]
+fn generated_source() {
+  let templates = TemplateLoader::new().load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/loader/templates")).unwrap();
+  let source = precompile(&templates).unwrap();
+
+  assert!(source.contains("\"shared/footer\" => shared_footer(data, out, eval_context),"));
+  assert!(source.contains("pub fn shared_footer(data: &dyn ::rumblebars::HBData, out: &mut dyn ::std::io::Write, "));
+  assert!(source.contains("fn shared_header_body(r: &mut ::rumblebars::compiled::Renderer, out: &mut ::rumblebars::SafeWriting)"));
+  assert!(source.contains("r.partial(out, None, None, shared_header_body)?;"));
+  assert!(source.contains("r.value(out, &[\"title\"], true, "));
+  // blocks of builtin helpers are compiled, with a syntax tree built once for replaced helpers
+  assert!(source.contains("r.each(out, &[\"links\"],"));
+  assert!(source.contains("static ENTRIES: ::std::sync::OnceLock<::rumblebars::Entries> = ::std::sync::OnceLock::new();"));
+  assert!(!source.contains("try!("));
+}
+
+#[test]
+fn contextual_templates() {
+  let templates = vec![
+    ("page".to_string(), parse_contextual("<p>\n  <a href=\"{{url}}\">").unwrap()),
+    ("plain".to_string(), parse("{{url}}").unwrap()),
+  ];
+  let errors = precompile(&templates).err().unwrap();
+
+  assert_eq!(errors.len(), 1);
+  assert!(match errors[0].kind { LoadErrorKind::Precompile(_) => true, _ => false });
+  assert_eq!(errors[0].to_string(), "page:2:12: contextual autoescaping of ‘url’ can't be precompiled");
+}
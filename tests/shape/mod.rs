@@ -0,0 +1,48 @@
+use serialize::json::Json;
+
+use rumblebars::{parse, EvalContext, DataReport};
+
+fn check(template: &str, data: &str, context: &EvalContext) -> DataReport {
+  parse(template).unwrap().check_data(&Json::from_str(data).unwrap(), context)
+}
+
+fn missing(report: &DataReport) -> Vec<String> {
+  report.missing.iter().map(|m| m.to_string()).collect()
+}
+
+#[test]
+fn all_branches() {
+  let report = check("{{#if flag}}{{a}}{{else}}{{b}}{{/if}}{{#unless a}}{{c.d}}{{/unless}}", r##"{"flag": true, "a": 1}"##, &EvalContext::new());
+
+  assert_eq!(missing(&report), vec!["b at 1:26", "c.d at 1:51"]);
+  assert!(report.unused.is_empty());
+  assert!(!report.is_valid());
+}
+
+#[test]
+fn lists() {
+  let report = check("{{#each items}}{{id}} {{note}} {{@index}} {{@root.title}}{{/each}}", r##"{"title": "t", "items": [{"id": 1, "note": "n"}, {"id": 2, "extra": 3}]}"##, &EvalContext::new());
+
+  assert!(report.is_valid());
+  assert_eq!(report.unused, vec!["items.extra"]);
+}
+
+#[test]
+fn sections() {
+  let report = check("{{#post}}{{title}} {{../site}} {{author}}{{/post}}{{^draft}}{{footer}}{{/draft}}", r##"{"post": {"title": "t"}, "site": "s", "extra": {"a": 1}}"##, &EvalContext::new());
+
+  assert_eq!(missing(&report), vec!["author in post at 1:32", "draft at 1:51", "footer at 1:61"]);
+  assert_eq!(report.unused, vec!["extra"]);
+}
+
+#[test]
+fn partials() {
+  let mut context = EvalContext::new();
+  context.register_partial("item".to_string(), parse("{{name}} {{price}}").unwrap());
+  context.register_partial("user".to_string(), parse("{{email}}").unwrap());
+
+  let report = check("{{#each items}}{{> item}}{{/each}}{{> user account}}{{> absent}}", r##"{"items": [{"name": "n"}], "account": {"id": 1}}"##, &context);
+
+  assert_eq!(missing(&report), vec!["price in each items at 1:10, in partial item", "email in > user account at 1:1, in partial user"]);
+  assert_eq!(report.unused, vec!["account.id"]);
+}
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use serialize::json::Json;
+
+use rumblebars::{parse, Template, EvalContext, HBData};
+
+fn render(template: &Template, json: &str) -> String {
+  let data = Json::from_str(json).unwrap();
+  let mut buf = Vec::new();
+  template.eval(&data, &mut buf, &EvalContext::new()).unwrap();
+  String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn literal() {
+  let source = "<ul>{{#items}}<li>{{name}}</li>{{else}}empty{{/items}}</ul>{{{raw}}} {{> item items.[0]}}";
+  let template = template!("<ul>{{#items}}<li>{{name}}</li>{{else}}empty{{/items}}</ul>{{{raw}}} {{> item items.[0]}}");
+
+  assert_eq!(format!("{:?}", template.entries), format!("{:?}", parse(source).unwrap().entries));
+}
+
+#[test]
+fn raw_literal() {
+  let template = template!(r#"{{#if ok}}"{{name}}"{{/if}}"#);
+
+  assert_eq!(render(&template, r##"{"ok": true, "name": "<b>"}"##), "\"&lt;b&gt;\"");
+}
+
+#[test]
+fn file() {
+  let template = template!(file = "tests/loader/templates/shared/header.hbs");
+
+  assert_eq!(render(&template, r##"{"title": "T"}"##), "<h1>T</h1>");
+}
+
+#[derive(HBData)]
+struct Blog {
+  title: String,
+  posts: Vec<Post>,
+  featured: Post,
+}
+
+#[derive(HBData)]
+struct Post {
+  #[hbdata(rename = "post-title")]
+  title: String,
+  tags: Vec<String>,
+}
+
+#[test]
+fn typed() {
+  let blog = Blog {
+    title: "blog".to_string(),
+    posts: vec![
+      Post { title: "first".to_string(), tags: vec!["a".to_string(), "b".to_string()] },
+      Post { title: "second".to_string(), tags: vec![] },
+    ],
+    featured: Post { title: "third".to_string(), tags: vec!["c".to_string()] },
+  };
+
+  let template = typed_template!(Blog, "{{title}}:{{#each posts}} {{post-title}} in {{../title}} ({{#each tags}}{{.}}{{@root.featured.post-title}}{{else}}none{{/each}}){{/each}} {{#with featured}}{{post-title}} {{tags.[0]}}{{/with}}");
+
+  assert_eq!(template.eval_to_string(&blog).unwrap(), "blog: first in blog (athirdbthird) second in blog (none) third c");
+}
+
+#[derive(HBData)]
+struct Page {
+  title: String,
+  meta: Json,
+  counts: BTreeMap<String, u32>,
+  author: Option<Author>,
+}
+
+#[derive(HBData)]
+struct Author {
+  name: String,
+}
+
+#[test]
+fn typed_runtime_keys() {
+  let mut counts = BTreeMap::new();
+  counts.insert("views".to_string(), 3);
+  let page = Page {
+    title: "page".to_string(),
+    meta: Json::from_str(r##"{"lang": "fr"}"##).unwrap(),
+    counts: counts,
+    author: Some(Author { name: "nico".to_string() }),
+  };
+
+  let mut context = EvalContext::new();
+  context.register_helper("csrf_token".to_string(), Box::new(|_, _, out, _| "a1b2".write_value(out)));
+
+  // json and map keys are only known at runtime, helpers are declared
+  let template = typed_template!(Page, helpers = [csrf_token], "{{title}} {{meta.lang}} {{counts.views}} {{#with author}}{{name}}{{/with}} {{author.name}} {{csrf_token}}");
+
+  let mut buf = Vec::new();
+  template.eval(&page, &mut buf, &context).unwrap();
+  assert_eq!(String::from_utf8(buf).unwrap(), "page fr 3 nico nico a1b2");
+}
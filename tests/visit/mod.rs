@@ -0,0 +1,75 @@
+use serialize::json::Json;
+
+use rumblebars::{parse, to_source, Visitor, Fold, HBExpression, walk_expression, noop_fold_expression};
+
+#[derive(Default)]
+struct Usage {
+  expressions: Vec<String>,
+  partials: Vec<String>,
+  paths: Vec<String>,
+  raw: usize,
+}
+
+impl Visitor for Usage {
+  fn visit_raw(&mut self, _raw: &str) {
+    self.raw += 1;
+  }
+
+  fn visit_expression(&mut self, exp: &HBExpression) {
+    self.expressions.push(exp.path());
+    walk_expression(self, exp);
+  }
+
+  fn visit_partial(&mut self, exp: &HBExpression) {
+    self.partials.push(exp.path());
+    walk_expression(self, exp);
+  }
+
+  fn visit_path(&mut self, path: &[String]) {
+    self.paths.push(path.join("."));
+  }
+}
+
+#[test]
+fn visitor() {
+  let template = parse("a{{#each items}}{{#if done}}{{> item .}}{{else}}{{link url \"x\" 1}}{{/if}}{{/each}}b").unwrap();
+  let mut usage = Usage::default();
+  usage.visit_entries(&template.entries);
+
+  assert_eq!(usage.expressions, vec!["each", "if", "link"]);
+  assert_eq!(usage.partials, vec!["item"]);
+  assert_eq!(usage.paths, vec!["items", "done", ".", "url"]);
+  assert_eq!(usage.raw, 2);
+}
+
+// moves data paths under `data`, the current context aside
+struct Nest;
+
+impl Fold for Nest {
+  fn fold_expression(&mut self, mut exp: HBExpression) -> HBExpression {
+    if exp.params.is_empty() && exp.block.is_none() && exp.base[0] != "." {
+      exp.base.insert(0, "data".to_string());
+    }
+    noop_fold_expression(self, exp)
+  }
+
+  fn fold_path(&mut self, mut path: Vec<String>) -> Vec<String> {
+    path.insert(0, "data".to_string());
+    path
+  }
+
+  fn fold_raw(&mut self, raw: String) -> String {
+    raw.to_uppercase()
+  }
+}
+
+#[test]
+fn fold() {
+  let mut template = parse("{{#if ok}}<b>{{name}}</b>{{else}}{{#each items}}{{.}}{{/each}}{{/if}}").unwrap();
+  template.entries = Nest.fold_entries(template.entries);
+
+  assert_eq!(to_source(&template.entries), "{{#if data.ok}}<B>{{data.name}}</B>{{else}}{{#each data.items}}{{.}}{{/each}}{{/if}}");
+
+  let json = Json::from_str(r##"{"data": {"ok": true, "name": "n"}}"##).unwrap();
+  assert_eq!(template.eval_to_string(&json).unwrap(), "<B>n</B>");
+}